@@ -1,10 +1,12 @@
-use crate::Chassis;
-use std::path::PathBuf;
+use crate::{Chassis, Kube, Tier};
+use std::path::{Path, PathBuf};
 
 /// Environment variables available to statusline
 pub struct Environment {
     /// Last command's return code
     pub ret_code: Option<u8>,
+    /// Return codes of all commands of the last pipeline, `PIPESTATUS` in bash
+    pub pipe_status: Vec<u8>,
     /// Jobs currently running
     pub jobs_count: usize,
     /// Last command's elapsed time, in us
@@ -21,4 +23,19 @@ pub struct Environment {
     pub chassis: Chassis,
     /// Cheernt home: dir and username
     pub current_home: Option<(PathBuf, String)>,
+    /// Current kubernetes context, if the working tree is deployed to kubernetes
+    pub kube: Option<Kube>,
+    /// Deployment tier, shared by the badge and the window title
+    pub tier: Option<Tier>,
+}
+
+impl Environment {
+    /// Where upward searches for project files stop. Files outside of the repository or the home
+    /// directory belong to some other project
+    #[must_use]
+    pub fn project_top(&self) -> Option<&Path> {
+        self.git_tree
+            .as_deref()
+            .or_else(|| self.current_home.as_ref().map(|(home, _)| home.as_path()))
+    }
 }
@@ -1,20 +1,30 @@
 use crate::{Environment, Pretty};
 
+pub mod battery;
 pub mod build_info;
+pub mod cloud;
+pub mod dev_env;
+pub mod disk_free;
 pub mod elapsed;
 pub mod git;
 pub mod hostuser;
 pub mod jobs;
+pub mod kube;
+pub mod load;
 pub mod mail;
 pub mod return_code;
 pub mod root_shell;
 pub mod separator;
 pub mod ssh;
+pub mod targets;
+pub mod tier;
 pub mod time;
+pub mod toolchain;
 pub mod venv;
 pub mod workdir;
 
 /// All available statusline block types
+#[derive(Copy, Clone, Debug)]
 #[non_exhaustive]
 pub enum Kind {
     /// Empty separator
@@ -37,8 +47,10 @@ pub enum Kind {
     GitTree,
     /// Build for ???
     BuildInfo,
-    /// Python virtual environment name and version
+    /// Python virtual environment name and version, from venv, conda or pyenv
     Venv,
+    /// Nix shell or direnv environment, and blocked `.envrc` warning
+    DevEnv,
     /// Working directory with username substitution, git repo path and R/O display
     Workdir,
     /// Previous task execution time
@@ -49,9 +61,54 @@ pub enum Kind {
     Ssh,
     /// Unseen mail count
     Mail,
+    /// Free space and inodes on working directory's filesystem, if running low
+    DiskFree,
+    /// Load average per CPU and pressure stall information, if the system is overloaded
+    Load,
+    /// Battery charge, on portable devices and if running low
+    Battery,
+    /// Deployment tier badge, like `PROD`
+    Tier,
+    /// Kubernetes context and namespace, if working tree is deployed to kubernetes
+    Kube,
+    /// Active AWS profile, gcloud project and Azure subscription
+    Cloud,
+    /// Language toolchain versions pinned by the project
+    Toolchain,
+    /// Makefile, justfile, `package.json` and cargo alias targets, for the extended line
+    Targets,
 }
 
 impl Kind {
+    /// Every block type, in declaration order
+    pub const ALL: [Kind; 25] = [
+        Self::Separator,
+        Self::Empty,
+        Self::Continue,
+        Self::Jobs,
+        Self::ReturnCode,
+        Self::RootShell,
+        Self::HostUser,
+        Self::GitRepo,
+        Self::GitTree,
+        Self::BuildInfo,
+        Self::Venv,
+        Self::DevEnv,
+        Self::Workdir,
+        Self::Elapsed,
+        Self::Time,
+        Self::Ssh,
+        Self::Mail,
+        Self::DiskFree,
+        Self::Load,
+        Self::Battery,
+        Self::Tier,
+        Self::Kube,
+        Self::Cloud,
+        Self::Toolchain,
+        Self::Targets,
+    ];
+
     /// Creates a block from given environment. These blocks can be pretty-printed and extended
     #[must_use]
     pub fn create_from_env(&self, env: &Environment) -> Box<dyn Extend> {
@@ -67,11 +124,20 @@ impl Kind {
             Self::GitTree => Box::new(git::Tree::from(env)),
             Self::BuildInfo => Box::new(build_info::BuildInfo::from(env)),
             Self::Venv => Box::new(venv::MaybeVenv::from(env)),
+            Self::DevEnv => Box::new(dev_env::DevEnv::from(env)),
             Self::Workdir => Box::new(workdir::Workdir::from(env)),
             Self::Elapsed => Box::new(elapsed::Elapsed::from(env)),
             Self::Time => Box::new(time::Time::from(env)),
             Self::Ssh => Box::new(ssh::Ssh::from(env)),
             Self::Mail => Box::new(mail::UnseenMail::from(env)),
+            Self::DiskFree => Box::new(disk_free::DiskFree::from(env)),
+            Self::Load => Box::new(load::Load::from(env)),
+            Self::Battery => Box::new(battery::MaybeBattery::from(env)),
+            Self::Tier => Box::new(tier::MaybeTier::from(env)),
+            Self::Kube => Box::new(kube::MaybeKube::from(env)),
+            Self::Cloud => Box::new(cloud::Cloud::from(env)),
+            Self::Toolchain => Box::new(toolchain::Toolchain::from(env)),
+            Self::Targets => Box::new(targets::Targets::from(env)),
         }
     }
 }
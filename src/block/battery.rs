@@ -0,0 +1,144 @@
+use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _};
+use std::{env, fs, path::Path};
+
+const POWER_SUPPLY: &str = "/sys/class/power_supply";
+
+#[derive(Debug, PartialEq, Eq)]
+enum Status {
+    Charging,
+    Discharging,
+    Full,
+}
+
+pub struct Battery {
+    /// Charge percentage, averaged over all system batteries
+    capacity: u32,
+    status: Status,
+}
+
+pub type MaybeBattery = Option<Battery>;
+
+impl Extend for MaybeBattery {
+    fn extend(self: Box<Self>) -> Box<dyn Pretty> {
+        self
+    }
+}
+
+/// Reads one system battery. Peripheral batteries (mice, headphones) have `Device` scope and are
+/// skipped
+fn read_battery(path: &Path) -> Option<(u32, Status)> {
+    let read = |name: &str| fs::read_to_string(path.join(name)).ok();
+    if read("type")?.trim() != "Battery" || read("scope").is_some_and(|s| s.trim() == "Device") {
+        return None;
+    }
+    let capacity = read("capacity")?.trim().parse().ok()?;
+    let status = match read("status").as_deref().map(str::trim) {
+        Some("Charging") => Status::Charging,
+        Some("Full") => Status::Full,
+        _ => Status::Discharging,
+    };
+    Some((capacity, status))
+}
+
+/// Averages the capacity. Charging wins if any battery charges, and full requires all of them
+fn aggregate(batteries: &[(u32, Status)]) -> Option<(u32, Status)> {
+    let count = u32::try_from(batteries.len()).ok().filter(|&n| n != 0)?;
+    let capacity = batteries.iter().map(|(capacity, _)| capacity).sum::<u32>() / count;
+    let status = if batteries.iter().any(|(_, s)| *s == Status::Charging) {
+        Status::Charging
+    } else if batteries.iter().all(|(_, s)| *s == Status::Full) {
+        Status::Full
+    } else {
+        Status::Discharging
+    };
+    Some((capacity, status))
+}
+
+impl From<&Environment> for MaybeBattery {
+    fn from(env: &Environment) -> Self {
+        let mode = env::var("PS1_BATTERY").unwrap_or_default();
+        let always = match mode.as_str() {
+            "never" => return None,
+            "always" => true,
+            _ => false,
+        };
+        if !always && !env.chassis.is_portable() {
+            return None;
+        }
+
+        let batteries = fs::read_dir(POWER_SUPPLY)
+            .ok()?
+            .map_while(Result::ok)
+            .filter_map(|entry| read_battery(&entry.path()))
+            .collect::<Vec<_>>();
+        let (capacity, status) = aggregate(&batteries)?;
+
+        let threshold = env::var("PS1_BATTERY_THRESHOLD")
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or(30);
+        (always || capacity <= threshold).then_some(Battery { capacity, status })
+    }
+}
+
+impl Icon for Battery {
+    fn icon(&self, mode: &IconMode) -> &'static str {
+        use IconMode::*;
+        const LEVELS: [&str; 11] = ["󰂎", "󰁺", "󰁻", "󰁼", "󰁽", "󰁾", "󰁿", "󰂀", "󰂁", "󰂂", "󰁹"];
+        const MINIMAL_LEVELS: [&str; 5] = ["", "", "", "", ""];
+        let level = self.capacity.min(100) as usize;
+        match (mode, &self.status) {
+            (Text, Status::Charging) => "bat+",
+            (Text, Status::Discharging | Status::Full) => "bat",
+            (Icons, Status::Charging) => "󰂄",
+            (Icons, Status::Discharging | Status::Full) => LEVELS[(level + 5) / 10],
+            (MinimalIcons, Status::Charging) => "",
+            (MinimalIcons, Status::Discharging | Status::Full) => MINIMAL_LEVELS[(level + 12) / 25],
+        }
+    }
+}
+
+impl Pretty for MaybeBattery {
+    fn pretty(&self, mode: &IconMode) -> Option<String> {
+        let battery = self.as_ref()?;
+        let text = format!("{} {}%", battery.icon(mode), battery.capacity);
+        let text = text.boxed();
+        let text = text.visible();
+        Some(
+            match (&battery.status, battery.capacity) {
+                (Status::Charging | Status::Full, _) => text.light_green(),
+                (Status::Discharging, 0..=10) => text.light_red(),
+                (Status::Discharging, _) => text.yellow(),
+            }
+            .with_reset()
+            .invisible()
+            .to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Status, aggregate};
+
+    #[test]
+    fn aggregation() {
+        use Status::*;
+        for (batteries, expected) in [
+            (vec![], None),
+            (vec![(42, Discharging)], Some((42, Discharging))),
+            (vec![(100, Full), (100, Full)], Some((100, Full))),
+            (
+                vec![(100, Full), (20, Discharging)],
+                Some((60, Discharging)),
+            ),
+            (vec![(100, Full), (21, Charging)], Some((60, Charging))),
+            (
+                vec![(10, Discharging), (90, Charging), (50, Full)],
+                Some((50, Charging)),
+            ),
+        ] {
+            assert_eq!(aggregate(&batteries), expected, "{batteries:?}");
+        }
+    }
+}
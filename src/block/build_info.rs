@@ -1,109 +1,210 @@
-use crate::{Environment, Extend, IconMode, Pretty, Style as _, file};
-use std::fmt::{Display, Formatter, Result as FmtResult};
-
-#[derive(Hash, PartialEq, Eq)]
-enum Kind {
-    Cargo,
-    Cmake,
-    Configure,
-    Flake,
-    Makefile,
-    Meson,
-    Install,
-    Jr,
-    NixShell,
-    Qbs,
-    Qmake,
-    Kks,
-    Gradle,
-}
+use crate::{Environment, Extend, IconMode, Pretty, Style as _, file, glob};
+use std::{
+    env,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
 
-impl Display for Kind {
-    fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        write!(f, "{}", match *self {
-            Self::Cargo => "cargo",
-            Self::Cmake => "cmake",
-            Self::Configure => "./configure",
-            Self::Flake => "flake",
-            Self::Makefile => "make",
-            Self::Meson => "meson",
-            Self::Install => "./install",
-            Self::Jr => "./jr",
-            Self::NixShell => "nix-shell",
-            Self::Qbs => "qbs",
-            Self::Qmake => "qmake",
-            Self::Kks => "kks",
-            Self::Gradle => "gradle",
-        })
-    }
+/// Marker prefix in `PS1_BUILD_RULES` which makes it searched in parent directories too
+const UPWARD: &str = ".../";
+
+/// How the tool is named once its marker is found
+#[derive(Clone, Copy)]
+enum Name<'a> {
+    Fixed(&'a str),
+    /// Chosen by looking around the marker, like for lockfiles next to `package.json`
+    Resolved(fn(&Path) -> &'static str),
 }
 
-pub struct BuildInfo(Vec<Kind>);
+/// Build tool detection rule
+#[derive(Clone, Copy)]
+struct Rule<'a> {
+    /// File name, or a glob like `*.pro`
+    marker: &'a str,
+    /// Whether parent directories are searched too, for tools which find their project root
+    upward: bool,
+    name: Name<'a>,
+}
 
-impl Extend for BuildInfo {
-    fn extend(self: Box<Self>) -> Box<dyn Pretty> {
-        self
+const fn here(marker: &'static str, name: &'static str) -> Rule<'static> {
+    Rule {
+        marker,
+        upward: false,
+        name: Name::Fixed(name),
     }
 }
 
-impl From<&Environment> for BuildInfo {
-    fn from(env: &Environment) -> Self {
-        let workdir = &env.work_dir;
-        let mut bi = vec![];
-
-        if file::points_to_file("flake.nix") {
-            bi.push(Kind::Flake);
-        }
-
-        if file::points_to_file("shell.nix") {
-            bi.push(Kind::NixShell);
-        }
-
-        if file::points_to_file("meson.build") {
-            bi.push(Kind::Meson);
-        }
-
-        if file::points_to_file("CMakeLists.txt") {
-            bi.push(Kind::Cmake);
-        }
-
-        if file::points_to_file("configure") {
-            bi.push(Kind::Configure);
-        }
+const fn up(marker: &'static str, name: &'static str) -> Rule<'static> {
+    Rule {
+        marker,
+        upward: true,
+        name: Name::Fixed(name),
+    }
+}
 
-        if file::points_to_file("Makefile") {
-            bi.push(Kind::Makefile);
-        }
+/// Built-in rules, in display order. Tools with several markers are shown once
+const RULES: [Rule; 38] = [
+    here("flake.nix", "flake"),
+    here("shell.nix", "nix-shell"),
+    here("meson.build", "meson"),
+    here("CMakeLists.txt", "cmake"),
+    here("configure", "./configure"),
+    here("Makefile", "make"),
+    here("install", "./install"),
+    here("jr", "./jr"),
+    here("*.qbs", "qbs"),
+    here("*.pro", "qmake"),
+    up("Cargo.toml", "cargo"),
+    up(".kks-workspace", "kks"),
+    here("gradle.properties", "gradle"),
+    Rule {
+        marker: "package.json",
+        upward: true,
+        name: Name::Resolved(node_tool),
+    },
+    up("go.mod", "go"),
+    here("pom.xml", "maven"),
+    up("MODULE.bazel", "bazel"),
+    up("WORKSPACE.bazel", "bazel"),
+    up("WORKSPACE", "bazel"),
+    here("BUILD.bazel", "bazel"),
+    up("justfile", "just"),
+    up("Justfile", "just"),
+    up("Taskfile.yml", "task"),
+    up("Taskfile.yaml", "task"),
+    Rule {
+        marker: "pyproject.toml",
+        upward: true,
+        name: Name::Resolved(python_tool),
+    },
+    here("tox.ini", "tox"),
+    here("Dockerfile", "docker"),
+    here("compose.yaml", "compose"),
+    here("compose.yml", "compose"),
+    here("docker-compose.yaml", "compose"),
+    here("docker-compose.yml", "compose"),
+    up("build.zig", "zig"),
+    up("dune-project", "dune"),
+    up("stack.yaml", "stack"),
+    up("mix.exs", "mix"),
+    here("build.sbt", "sbt"),
+    here("build.xml", "ant"),
+    here("SConstruct", "scons"),
+];
 
-        if file::points_to_file("install") {
-            bi.push(Kind::Install);
-        }
+/// Package manager owning the lockfile next to `package.json`, or above it in a workspace
+pub(crate) fn node_tool(package: &Path) -> &'static str {
+    let dir = package.parent().unwrap_or(package);
+    [
+        ("bun.lock", "bun"),
+        ("bun.lockb", "bun"),
+        ("pnpm-lock.yaml", "pnpm"),
+        ("yarn.lock", "yarn"),
+    ]
+    .into_iter()
+    .find(|(lockfile, _)| file::upfind(dir, lockfile).is_ok())
+    .map_or("npm", |(_, tool)| tool)
+}
 
-        if file::points_to_file("jr") {
-            bi.push(Kind::Jr);
-        }
+/// Project manager by its lockfile or its `[tool.*]` section in `pyproject.toml`
+fn python_tool(pyproject: &Path) -> &'static str {
+    let dir = pyproject.parent().unwrap_or(pyproject);
+    let text = fs::read_to_string(pyproject).unwrap_or_default();
+    [
+        ("uv.lock", "uv"),
+        ("poetry.lock", "poetry"),
+        ("pdm.lock", "pdm"),
+    ]
+    .into_iter()
+    .find(|(lockfile, _)| file::points_to_file(&dir.join(lockfile)))
+    .or_else(|| {
+        ["uv", "poetry", "hatch", "pdm"]
+            .into_iter()
+            .find(|tool| text.contains(&format!("[tool.{tool}")))
+            .map(|tool| ("", tool))
+    })
+    .map_or("pip", |(_, tool)| tool)
+}
 
-        if let Ok(true) = file::exists_that(workdir, |filename| filename.ends_with(".qbs")) {
-            bi.push(Kind::Qbs);
-        }
+/// Parses `PS1_BUILD_RULES`, like `bazel=.../WORKSPACE ninja=build.ninja`
+fn parse_rules(rules: &str) -> impl Iterator<Item = Rule<'_>> {
+    rules.split_whitespace().filter_map(|rule| {
+        let (name, marker) = rule.split_once('=')?;
+        let (marker, upward) = match marker.strip_prefix(UPWARD) {
+            Some(marker) => (marker, true),
+            None => (marker, false),
+        };
+        (!name.is_empty() && !marker.is_empty()).then_some(Rule {
+            marker,
+            upward,
+            name: Name::Fixed(name),
+        })
+    })
+}
 
-        if let Ok(true) = file::exists_that(workdir, |filename| filename.ends_with(".pro")) {
-            bi.push(Kind::Qmake);
-        }
+/// Finds the marker in the directory. Globs are matched against all file names
+fn find(dir: &Path, marker: &str) -> Option<PathBuf> {
+    if !marker.contains(['*', '?']) {
+        return Some(dir.join(marker)).filter(file::points_to_file);
+    }
+    fs::read_dir(dir)
+        .ok()?
+        .map_while(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_name()
+                .and_then(OsStr::to_str)
+                .is_some_and(|name| glob::matches(marker, name))
+                && file::points_to_file(path)
+        })
+}
 
-        if file::upfind(workdir, "Cargo.toml").is_ok() {
-            bi.push(Kind::Cargo);
+/// Names of the tools whose markers are found, built-in rules first. Upward search stops at `top`
+fn detect<'a, I: IntoIterator<Item = Rule<'a>>>(
+    workdir: &Path,
+    top: Option<&Path>,
+    rules: I,
+) -> Vec<&'a str> {
+    let mut tools = vec![];
+    for rule in rules {
+        let found = if rule.upward {
+            file::ancestors_until(workdir, top).find_map(|dir| find(dir, rule.marker))
+        } else {
+            find(workdir, rule.marker)
+        };
+        let Some(path) = found else {
+            continue;
+        };
+        let tool = match rule.name {
+            Name::Fixed(name) => name,
+            Name::Resolved(resolve) => resolve(&path),
+        };
+        if !tools.contains(&tool) {
+            tools.push(tool);
         }
+    }
+    tools
+}
 
-        if file::upfind(workdir, ".kks-workspace").is_ok() {
-            bi.push(Kind::Kks);
-        }
+pub struct BuildInfo(Vec<String>);
 
-        if file::points_to_file("gradle.properties") {
-            bi.push(Kind::Gradle);
-        }
+impl Extend for BuildInfo {
+    fn extend(self: Box<Self>) -> Box<dyn Pretty> {
+        self
+    }
+}
 
-        Self(bi)
+impl From<&Environment> for BuildInfo {
+    fn from(env: &Environment) -> Self {
+        let custom = env::var("PS1_BUILD_RULES").unwrap_or_default();
+        let rules = RULES.into_iter().chain(parse_rules(&custom));
+        Self(
+            detect(&env.work_dir, env.project_top(), rules)
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+        )
     }
 }
 
@@ -115,9 +216,6 @@ impl Pretty for BuildInfo {
         }
         Some(
             buildinfo
-                .iter()
-                .map(ToString::to_string)
-                .collect::<Vec<_>>()
                 .join(" ")
                 .boxed()
                 .visible()
@@ -128,3 +226,106 @@ impl Pretty for BuildInfo {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{RULES, detect, parse_rules};
+    use crate::probe::fixture::Fixture;
+
+    type Case<'a> = (&'a [(&'a str, &'a str)], &'a str, &'a [&'a str]);
+
+    #[test]
+    fn markers() {
+        let cases: &[Case] = &[
+            (&[("/p/Makefile", "")], "/p", &["make"]),
+            (&[("/p/Makefile", "")], "/p/src/", &[]),
+            (
+                &[("/p/Cargo.toml", ""), ("/p/src/", "")],
+                "/p/src",
+                &["cargo"],
+            ),
+            (
+                &[("/p/app.pro", ""), ("/p/app.qbs", "")],
+                "/p",
+                &["qbs", "qmake"],
+            ),
+            (&[("/p/package.json", "{}")], "/p", &["npm"]),
+            (
+                &[
+                    ("/p/package.json", "{}"),
+                    ("/p/pnpm-lock.yaml", ""),
+                    ("/p/pkg/a/package.json", "{}"),
+                ],
+                "/p/pkg/a",
+                &["pnpm"],
+            ),
+            (
+                &[("/p/pyproject.toml", "[tool.poetry]\n")],
+                "/p",
+                &["poetry"],
+            ),
+            (
+                &[("/p/pyproject.toml", "[tool.poetry]\n"), ("/p/uv.lock", "")],
+                "/p",
+                &["uv"],
+            ),
+            (&[("/p/pyproject.toml", "[project]\n")], "/p", &["pip"]),
+            (
+                &[("/p/WORKSPACE", ""), ("/p/lib/BUILD.bazel", "")],
+                "/p/lib",
+                &["bazel"],
+            ),
+            (
+                &[
+                    ("/p/Dockerfile", ""),
+                    ("/p/compose.yaml", ""),
+                    ("/p/justfile", ""),
+                ],
+                "/p",
+                &["just", "docker", "compose"],
+            ),
+        ];
+        for (files, workdir, expected) in cases {
+            let fixture = Fixture::new(files);
+            let workdir = fixture.probe().path(workdir);
+            assert_eq!(
+                detect(&workdir, None, RULES),
+                *expected,
+                "{files:?} in {workdir:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn configured() {
+        let fixture = Fixture::new(&[("/p/build.ninja", ""), ("/p/src/", ""), ("/p/.hg/", "")]);
+        let workdir = fixture.probe().path("/p/src");
+        let rules = "ninja=.../build.ninja hg=.../.hg broken= =x ninja=build.ninja";
+        assert_eq!(
+            detect(&workdir, None, parse_rules(rules)),
+            ["ninja"],
+            "directories are not markers, names are shown once"
+        );
+    }
+
+    #[test]
+    fn bounded() {
+        let fixture = Fixture::new(&[
+            ("/p/justfile", ""),
+            ("/p/repo/Cargo.toml", ""),
+            ("/p/repo/src/", ""),
+        ]);
+        let workdir = fixture.probe().path("/p/repo/src");
+        let top = fixture.probe().path("/p/repo");
+        assert_eq!(
+            detect(&workdir, Some(&top), RULES),
+            ["cargo"],
+            "markers above the top are ignored"
+        );
+        assert_eq!(
+            detect(&workdir, Some(&fixture.probe().path("/q")), RULES),
+            ["cargo", "just"],
+            "top which is not an ancestor is ignored"
+        );
+    }
+}
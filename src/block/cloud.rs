@@ -0,0 +1,207 @@
+use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _, ini, json::Json};
+use chrono::{DateTime, Utc};
+use std::{env, fs, path::PathBuf};
+
+#[derive(Clone, Copy)]
+enum Provider {
+    Aws,
+    Gcp,
+    Azure,
+}
+
+/// Active profile, project or subscription of a cloud CLI
+struct Identity {
+    provider: Provider,
+    name: String,
+    region: Option<String>,
+    expired: bool,
+}
+
+pub struct Cloud(Vec<Identity>);
+
+impl Extend for Cloud {
+    fn extend(self: Box<Self>) -> Box<dyn Pretty> {
+        self
+    }
+}
+
+/// Path from environment variable, or the default one in home directory
+fn config_dir(var: &str, default: &str) -> PathBuf {
+    env::var(var).map_or_else(
+        |_| PathBuf::from(env::var("HOME").unwrap_or_default()).join(default),
+        PathBuf::from,
+    )
+}
+
+/// Profile chosen with `AWS_PROFILE` or `aws-vault exec`, and its region
+fn aws() -> Option<Identity> {
+    let name = env::var("AWS_VAULT")
+        .or_else(|_| env::var("AWS_PROFILE"))
+        .ok()
+        .filter(|name| !name.is_empty())?;
+    let region = env::var("AWS_REGION")
+        .or_else(|_| env::var("AWS_DEFAULT_REGION"))
+        .ok()
+        .or_else(|| {
+            let config = config_dir("AWS_CONFIG_FILE", ".aws/config");
+            let section = if name == "default" {
+                "default".to_owned()
+            } else {
+                format!("profile {name}")
+            };
+            ini::value(&fs::read_to_string(config).ok()?, &section, "region")
+        });
+    // aws-vault sets it for temporary credentials, which stop working silently
+    let expired = env::var("AWS_SESSION_EXPIRATION")
+        .is_ok_and(|expiration| is_expired(&expiration, Utc::now()));
+    Some(Identity {
+        provider: Provider::Aws,
+        name,
+        region,
+        expired,
+    })
+}
+
+/// Whether RFC 3339 expiration time has passed. Unparsable time is not expired
+fn is_expired(expiration: &str, now: DateTime<Utc>) -> bool {
+    DateTime::parse_from_rfc3339(expiration.trim()).is_ok_and(|time| time < now)
+}
+
+/// Project of the active gcloud configuration
+fn gcp() -> Option<Identity> {
+    let dir = config_dir("CLOUDSDK_CONFIG", ".config/gcloud");
+    let config = env::var("CLOUDSDK_ACTIVE_CONFIG_NAME")
+        .ok()
+        .or_else(|| {
+            Some(
+                fs::read_to_string(dir.join("active_config"))
+                    .ok()?
+                    .trim()
+                    .to_owned(),
+            )
+        })
+        .filter(|config| !config.is_empty())?;
+    let name = env::var("CLOUDSDK_CORE_PROJECT").ok().or_else(|| {
+        let text = fs::read_to_string(dir.join("configurations").join(format!("config_{config}")));
+        ini::value(&text.ok()?, "core", "project")
+    })?;
+    Some(Identity {
+        provider: Provider::Gcp,
+        name,
+        region: None,
+        expired: false,
+    })
+}
+
+/// Default Azure subscription
+fn azure() -> Option<Identity> {
+    let path = config_dir("AZURE_CONFIG_DIR", ".azure").join("azureProfile.json");
+    let profile = Json::parse(&fs::read_to_string(path).ok()?)?;
+    let name = profile
+        .get("subscriptions")?
+        .as_array()?
+        .iter()
+        .find(|sub| sub.get("isDefault").and_then(Json::as_bool) == Some(true))?
+        .get("name")?
+        .as_str()?
+        .to_owned();
+    Some(Identity {
+        provider: Provider::Azure,
+        name,
+        region: None,
+        expired: false,
+    })
+}
+
+impl From<&Environment> for Cloud {
+    fn from(_: &Environment) -> Self {
+        let enabled = env::var("PS1_CLOUD").unwrap_or_else(|_| "aws,gcp,azure".to_owned());
+        let enabled = enabled.split(',').map(str::trim).collect::<Vec<_>>();
+        Cloud(
+            [
+                ("aws", aws as fn() -> Option<Identity>),
+                ("gcp", gcp),
+                ("azure", azure),
+            ]
+            .into_iter()
+            .filter(|(name, _)| enabled.contains(name))
+            .filter_map(|(_, identity)| identity())
+            .collect(),
+        )
+    }
+}
+
+impl Icon for Provider {
+    fn icon(&self, mode: &IconMode) -> &'static str {
+        use IconMode::*;
+        match (mode, self) {
+            (Text, Provider::Aws) => "aws",
+            (Text, Provider::Gcp) => "gcp",
+            (Text, Provider::Azure) => "az",
+            (Icons, Provider::Aws) => "",
+            (Icons, Provider::Gcp) => "󱇶",
+            (Icons, Provider::Azure) => "󰠅",
+            (MinimalIcons, _) => "",
+        }
+    }
+}
+
+impl Pretty for Identity {
+    fn pretty(&self, mode: &IconMode) -> Option<String> {
+        let mut text = format!("{} {}", self.provider.icon(mode), self.name);
+        if let Some(region) = &self.region {
+            text = format!("{text}@{region}");
+        }
+        if self.expired {
+            text = format!("{text} expired");
+        }
+        let text = text.boxed();
+        let text = text.visible();
+        Some(
+            match (self.expired, self.provider) {
+                (true, _) => text.light_red(),
+                (false, Provider::Aws) => text.yellow(),
+                (false, Provider::Gcp) => text.blue(),
+                (false, Provider::Azure) => text.cyan(),
+            }
+            .with_reset()
+            .invisible()
+            .to_string(),
+        )
+    }
+}
+
+impl Pretty for Cloud {
+    fn pretty(&self, mode: &IconMode) -> Option<String> {
+        let parts = self
+            .0
+            .iter()
+            .filter_map(|identity| identity.pretty(mode))
+            .collect::<Vec<_>>();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_expired;
+    use chrono::DateTime;
+
+    #[test]
+    fn aws_expiration() {
+        let now = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+            .unwrap()
+            .to_utc();
+        for (expiration, expected) in [
+            ("2024-05-01T11:59:59Z", true),
+            ("2024-05-01T12:00:01Z", false),
+            ("2024-05-01T13:30:00+02:00", true),
+            ("2024-05-01T10:30:00-02:00", false),
+            ("2024-05-01T11:00:00Z\n", true),
+            ("2024-05-01", false),
+            ("", false),
+        ] {
+            assert_eq!(is_expired(expiration, now), expected, "{expiration:?}");
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _, file};
+use orion::hazardous::hash::sha2::sha256::Sha256;
+use std::{
+    env,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Nix development shell
+enum Nix {
+    /// `nix-shell --pure`
+    Pure(String),
+    /// `nix-shell` or `nix develop`, which keep the outer environment
+    Impure(String),
+}
+
+/// Nix shell and direnv environments, and `.envrc` which direnv refuses to load
+pub struct DevEnv {
+    nix: Option<Nix>,
+    /// Directory direnv has loaded `.envrc` from
+    direnv: Option<String>,
+    /// Nearest `.envrc` is not allowed with `direnv allow`, or is denied
+    blocked: bool,
+}
+
+impl Extend for DevEnv {
+    fn extend(self: Box<Self>) -> Box<dyn Pretty> {
+        self
+    }
+}
+
+/// Allow database key of `.envrc`: sha256 of its absolute path, a newline and its contents
+fn allow_hash(path: &str, contents: &[u8]) -> Option<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(path.as_bytes()).ok()?;
+    hasher.update(b"\n").ok()?;
+    hasher.update(contents).ok()?;
+    let digest = hasher.finalize().ok()?;
+    Some(digest.as_ref().iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{byte:02x}").unwrap_or_default();
+        hex
+    }))
+}
+
+/// Whether direnv would load this `.envrc`. Denied files are listed under `deny` by the hash of
+/// their path only
+fn is_allowed(envrc: &Path, direnv_data: &Path) -> bool {
+    let (Some(path), Ok(contents)) = (envrc.to_str(), fs::read(envrc)) else {
+        return true;
+    };
+    let allowed = allow_hash(path, &contents)
+        .is_some_and(|hash| file::exists(&direnv_data.join("allow").join(hash)));
+    let denied = allow_hash(path, b"")
+        .is_some_and(|hash| file::exists(&direnv_data.join("deny").join(hash)));
+    allowed && !denied
+}
+
+/// `$XDG_DATA_HOME/direnv`
+fn direnv_data() -> PathBuf {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".local/share"))
+        .join("direnv")
+}
+
+fn nix() -> Option<Nix> {
+    let kind = env::var("IN_NIX_SHELL").ok()?;
+    // `nix develop` names the shell after the derivation, `mkShell` defaults to `nix-shell`
+    let name = env::var("name")
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or("nix-shell".to_owned());
+    Some(if kind == "pure" {
+        Nix::Pure(name)
+    } else {
+        Nix::Impure(name)
+    })
+}
+
+/// Directory which direnv has loaded, from `DIRENV_DIR` like `-/home/user/project`
+fn direnv() -> Option<PathBuf> {
+    let dir = env::var("DIRENV_DIR").ok()?;
+    env::var_os("DIRENV_DIFF")?;
+    Some(PathBuf::from(dir.strip_prefix('-').unwrap_or(&dir)))
+}
+
+impl From<&Environment> for DevEnv {
+    fn from(env: &Environment) -> Self {
+        let loaded = direnv();
+        let data = direnv_data();
+        // direnv resolves `.envrc` against logical `$PWD`, while working directory is physical
+        let candidates = [
+            env::var("PWD")
+                .ok()
+                .and_then(|pwd| file::upfind(pwd, ".envrc").ok()),
+            file::upfind(&env.work_dir, ".envrc").ok(),
+        ];
+        let nearest = candidates.iter().flatten().next();
+        let blocked = file::exists(&data.join("allow"))
+            && nearest.is_some_and(|envrc| envrc.parent() != loaded.as_deref())
+            && candidates
+                .iter()
+                .flatten()
+                .all(|path| !is_allowed(path, &data));
+        let direnv = loaded.map(|dir| {
+            dir.file_name()
+                .map_or("/".into(), |name| name.to_string_lossy().into_owned())
+        });
+        DevEnv {
+            nix: nix(),
+            direnv,
+            blocked,
+        }
+    }
+}
+
+impl Icon for Nix {
+    fn icon(&self, mode: &IconMode) -> &'static str {
+        use IconMode::*;
+        match mode {
+            Text => "nix",
+            Icons | MinimalIcons => "",
+        }
+    }
+}
+
+impl Pretty for DevEnv {
+    fn pretty(&self, mode: &IconMode) -> Option<String> {
+        use IconMode::*;
+        let mut parts = vec![];
+        match &self.nix {
+            Some(nix @ Nix::Pure(name)) => parts.push(format!("{} {name} pure", nix.icon(mode))),
+            Some(nix @ Nix::Impure(name)) => parts.push(format!("{} {name}", nix.icon(mode))),
+            None => {}
+        }
+        if let Some(dir) = &self.direnv {
+            let icon = match mode {
+                Text => "direnv",
+                Icons | MinimalIcons => "",
+            };
+            parts.push(format!("{icon} {dir}"));
+        }
+
+        let active = (!parts.is_empty()).then(|| {
+            parts
+                .join(" ")
+                .boxed()
+                .visible()
+                .blue()
+                .with_reset()
+                .invisible()
+                .to_string()
+        });
+        let blocked = self.blocked.then(|| {
+            let icon = match mode {
+                Text => "!",
+                Icons | MinimalIcons => "",
+            };
+            format!("{icon} .envrc blocked")
+                .boxed()
+                .visible()
+                .yellow()
+                .with_reset()
+                .invisible()
+                .to_string()
+        });
+        let shown = [active, blocked].into_iter().flatten().collect::<Vec<_>>();
+        (!shown.is_empty()).then(|| shown.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{allow_hash, is_allowed};
+    use crate::probe::fixture::Fixture;
+    use std::fs;
+
+    #[test]
+    fn hash() {
+        assert_eq!(
+            allow_hash("/p/.envrc", b"use flake\n").as_deref(),
+            Some("8da843c15ac6861818f052d8213b8381ec35931c49cbcf81188070eddfde873f"),
+            "same as direnv"
+        );
+    }
+
+    #[test]
+    fn allow_database() {
+        let fixture = Fixture::new(&[
+            ("/p/.envrc", "use flake\n"),
+            ("/data/allow/", ""),
+            ("/data/deny/", ""),
+        ]);
+        let probe = fixture.probe();
+        let (envrc, data) = (probe.path("/p/.envrc"), probe.path("/data"));
+        let path = envrc.to_str().expect("fixture path is utf-8");
+        assert!(!is_allowed(&envrc, &data), "not allowed yet");
+
+        let allow = allow_hash(path, b"use flake\n").expect("hash");
+        fs::write(data.join("allow").join(allow), path).expect("allow is writable");
+        assert!(is_allowed(&envrc, &data), "allowed");
+
+        let deny = allow_hash(path, b"").expect("hash");
+        fs::write(data.join("deny").join(deny), path).expect("deny is writable");
+        assert!(!is_allowed(&envrc, &data), "denied");
+
+        fs::write(&envrc, "use flake\nexport X=1\n").expect("envrc is writable");
+        assert!(!is_allowed(&envrc, &data), "changed since allowed");
+    }
+}
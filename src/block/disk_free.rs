@@ -0,0 +1,238 @@
+use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _};
+use rustix::fs as rfs;
+use std::env;
+
+/// Free space limit, either in percents of the total or as an absolute value
+#[derive(Debug, PartialEq, Eq)]
+enum Threshold {
+    Percent(u64),
+    Absolute(u64),
+}
+
+impl Threshold {
+    /// Parses `10%`, `512M`, `2G` or plain number. Suffixes are binary, as in `df -h`
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if let Some(percent) = s.strip_suffix('%') {
+            return Some(Self::Percent(percent.trim().parse().ok()?));
+        }
+        let (number, shift): (_, u32) = match s.char_indices().last()? {
+            (i, 'k' | 'K') => (&s[..i], 10),
+            (i, 'M') => (&s[..i], 20),
+            (i, 'G') => (&s[..i], 30),
+            (i, 'T') => (&s[..i], 40),
+            _ => (s, 0),
+        };
+        number
+            .trim()
+            .parse::<u64>()
+            .ok()?
+            .checked_mul(1 << shift)
+            .map(Self::Absolute)
+    }
+
+    fn from_env(var: &str, default: Self) -> Self {
+        env::var(var)
+            .ok()
+            .and_then(|s| Self::parse(&s))
+            .unwrap_or(default)
+    }
+
+    fn reached(&self, free: u64, total: u64) -> bool {
+        match *self {
+            Self::Percent(percent) => {
+                u128::from(free) * 100 < u128::from(total) * u128::from(percent)
+            }
+            Self::Absolute(limit) => free < limit,
+        }
+    }
+}
+
+enum Resource {
+    Space,
+    Inodes,
+}
+
+impl Icon for Resource {
+    fn icon(&self, mode: &IconMode) -> &'static str {
+        use IconMode::*;
+        match self {
+            Self::Space => match mode {
+                Text => "disk ",
+                Icons | MinimalIcons => "󰋊 ",
+            },
+            Self::Inodes => match mode {
+                Text => "inodes ",
+                Icons => "󰈔 ",
+                MinimalIcons => "# ",
+            },
+        }
+    }
+}
+
+pub struct DiskFree {
+    /// Bytes available to unprivileged user, if below threshold
+    space: Option<u64>,
+    /// Inodes available to unprivileged user, if below threshold
+    inodes: Option<u64>,
+}
+
+impl Extend for DiskFree {
+    fn extend(self: Box<Self>) -> Box<dyn Pretty> {
+        self
+    }
+}
+
+impl From<&Environment> for DiskFree {
+    fn from(env: &Environment) -> Self {
+        let Ok(stat) = rfs::statvfs(&env.work_dir) else {
+            return DiskFree {
+                space: None,
+                inodes: None,
+            };
+        };
+
+        let space_threshold = Threshold::from_env("PS1_DISK_THRESHOLD", Threshold::Percent(10));
+        let inode_threshold = Threshold::from_env("PS1_INODE_THRESHOLD", Threshold::Percent(10));
+
+        let block = stat.f_frsize.max(1);
+        let free = stat.f_bavail.saturating_mul(block);
+        let total = stat.f_blocks.saturating_mul(block);
+
+        // Filesystems with dynamic inode allocation (btrfs, for example) report zero inodes
+        DiskFree {
+            space: (total != 0 && space_threshold.reached(free, total)).then_some(free),
+            inodes: (stat.f_files != 0 && inode_threshold.reached(stat.f_favail, stat.f_files))
+                .then_some(stat.f_favail),
+        }
+    }
+}
+
+impl Pretty for DiskFree {
+    fn pretty(&self, mode: &IconMode) -> Option<String> {
+        let parts = [
+            self.space
+                .map(|free| format!("{}{}", Resource::Space.icon(mode), human_size(free))),
+            self.inodes
+                .map(|free| format!("{}{}", Resource::Inodes.icon(mode), human_count(free))),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+        if parts.is_empty() {
+            None?;
+        }
+
+        let text = parts.join(" ");
+        let text = text.boxed();
+        let text = text.visible();
+        let exhausted = self.space == Some(0) || self.inodes == Some(0);
+        Some(
+            if exhausted { text.red() } else { text.yellow() }
+                .bold()
+                .with_reset()
+                .invisible()
+                .to_string(),
+        )
+    }
+}
+
+/// Formats a size in bytes with binary suffix and at most one decimal digit: `1.5G`, `300M`,
+/// `12k`
+fn human_size(value: u64) -> String {
+    scaled(value, 1024)
+}
+
+/// Formats a count, like of inodes, with decimal suffix: `1.2M` is 1 200 000
+fn human_count(value: u64) -> String {
+    scaled(value, 1000)
+}
+
+fn scaled(value: u64, base: u64) -> String {
+    let mut scaled = value;
+    let mut rem = 0;
+    let mut suffix = "";
+    for next in ["k", "M", "G", "T", "P"] {
+        if scaled < base {
+            break;
+        }
+        rem = scaled % base;
+        scaled /= base;
+        suffix = next;
+    }
+    if scaled < 10 && !suffix.is_empty() {
+        format!("{scaled}.{}{suffix}", rem * 10 / base)
+    } else {
+        format!("{scaled}{suffix}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Threshold, human_count, human_size};
+
+    const MIB: u64 = 1 << 20;
+    const GIB: u64 = 1 << 30;
+
+    #[test]
+    fn thresholds() {
+        for (text, expected) in [
+            ("10%", Some(Threshold::Percent(10))),
+            (" 5 % ", Some(Threshold::Percent(5))),
+            ("2G", Some(Threshold::Absolute(2 * GIB))),
+            ("512M", Some(Threshold::Absolute(512 * MIB))),
+            ("4k", Some(Threshold::Absolute(4096))),
+            ("1000", Some(Threshold::Absolute(1000))),
+            ("", None),
+            ("G", None),
+            ("ten%", None),
+            ("99999999T", None),
+        ] {
+            assert_eq!(Threshold::parse(text), expected, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn reached() {
+        for (threshold, free, total, expected) in [
+            (Threshold::Percent(10), 9, 100, true),
+            (Threshold::Percent(10), 10, 100, false),
+            (Threshold::Percent(100), u64::MAX - 1, u64::MAX, true),
+            (Threshold::Absolute(2 * GIB), GIB, 1024 * GIB, true),
+            (Threshold::Absolute(2 * GIB), 2 * GIB, 1024 * GIB, false),
+        ] {
+            assert_eq!(
+                threshold.reached(free, total),
+                expected,
+                "{threshold:?} with {free} of {total}"
+            );
+        }
+    }
+
+    #[test]
+    fn human_sizes() {
+        for (value, expected) in [
+            (0, "0"),
+            (1023, "1023"),
+            (1536, "1.5k"),
+            (300 * MIB, "300M"),
+            (3 * GIB + GIB / 2, "3.5G"),
+        ] {
+            assert_eq!(human_size(value), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn human_counts() {
+        for (value, expected) in [
+            (999, "999"),
+            (1000, "1.0k"),
+            (1536, "1.5k"),
+            (1_200_000, "1.2M"),
+            (64_000, "64k"),
+        ] {
+            assert_eq!(human_count(value), expected, "{value}");
+        }
+    }
+}
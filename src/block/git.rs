@@ -1,4 +1,4 @@
-use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _, file};
+use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _, cancel, file, hyperlink};
 use anyhow::{Context as _, Result, anyhow, bail};
 use memmapix::Mmap;
 use rustix::process;
@@ -400,6 +400,18 @@ fn get_remote(head: &Head) -> Option<(String, String)> {
     remote_name.zip(remote_branch)
 }
 
+/// Fetch URL of the remote, from `[remote "name"]` section
+fn get_remote_url(head: &Head, remote: &str) -> Option<String> {
+    let section = format!("[remote \"{remote}\"]");
+    BufReader::new(fs::File::open(head.root.join("config")).ok()?)
+        .lines()
+        .map_while(Result::ok)
+        .skip_while(|x| x != &section)
+        .skip(1)
+        .take_while(|x| x.starts_with('\t'))
+        .find_map(|line| Some(line.strip_prefix("\turl = ")?.to_owned()))
+}
+
 fn get_ahead_behind(
     tree: &Path,
     head: &HeadKind,
@@ -431,6 +443,8 @@ fn get_ahead_behind(
 pub struct GitRepo {
     head: Head,
     remote: Option<(String, String)>,
+    /// Page of the upstream branch on its forge
+    link: Option<String>,
     stashes: usize,
     state: Option<State>,
     behind: usize,
@@ -513,6 +527,9 @@ impl From<&Environment> for Repo {
         let head = head.fix_nonexistent();
 
         let remote = get_remote(&head);
+        let link = remote.as_ref().and_then(|(name, branch)| {
+            hyperlink::branch_url(&get_remote_url(&head, name)?, branch)
+        });
 
         let (ahead, behind) =
             get_ahead_behind(&tree, &head.kind, remote.as_ref()).unwrap_or((0, 0));
@@ -520,6 +537,7 @@ impl From<&Environment> for Repo {
         Ok(GitRepo {
             head,
             remote,
+            link,
             stashes,
             state,
             behind,
@@ -539,9 +557,10 @@ impl Extend for Tree {
         let Some(self_ref) = *self else { return self };
 
         let parent_pid = process::getpid();
+        let mut command = Command::new("git");
         // SAFETY: pre_exec only sets parent process death signal and does nothing more
-        let out = unsafe {
-            Command::new("git")
+        let out = cancel::stdout(unsafe {
+            command
                 .arg("-C")
                 .arg(&self_ref.tree)
                 .arg("status")
@@ -553,13 +572,11 @@ impl Extend for Tree {
                     }
                     Ok(())
                 })
-                .output()
-                .ok()
-        };
+        });
         let Some(out) = out else {
             return Box::new(self_ref);
         };
-        let lines = out.stdout.split(|&c| c == b'\n').peekable();
+        let lines = out.split(|&c| c == b'\n').peekable();
 
         let mut unmerged = 0;
         let mut staged = 0;
@@ -615,7 +632,12 @@ impl Pretty for GitRepo {
             res.push(format!("{}|", state.pretty(mode).unwrap_or_default()));
         }
 
-        let head = self.head.pretty(mode).unwrap_or_default();
+        let head = match (&self.head.kind, &self.link) {
+            (branch @ HeadKind::Branch(name), Some(url)) => {
+                format!("{} {}", branch.icon(mode), name.hyperlink(url))
+            }
+            _ => self.head.pretty(mode).unwrap_or_default(),
+        };
         res.push(head);
 
         match (&self.head.kind, &self.remote) {
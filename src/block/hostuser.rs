@@ -1,6 +1,7 @@
-use crate::{Chassis, Environment, Extend, Icon, IconMode, Pretty, Style as _};
+use crate::{Chassis, Environment, Extend, Icon, IconMode, Pretty, Probe, Style as _, virt};
 
-struct Host(Chassis, String);
+/// Chassis, hostname and container name if any
+struct Host(Chassis, String, Option<String>);
 struct User(String);
 pub struct HostUser(User, Host);
 
@@ -12,7 +13,11 @@ impl Extend for HostUser {
 
 impl From<&Environment> for Host {
     fn from(env: &Environment) -> Self {
-        Host(env.chassis, env.host.clone())
+        let container = match env.chassis {
+            Chassis::Container(_) => virt::container_name(&Probe::default()),
+            _ => None,
+        };
+        Host(env.chassis, env.host.clone(), container)
     }
 }
 
@@ -46,16 +51,31 @@ impl Icon for User {
 
 impl Pretty for Host {
     fn pretty(&self, mode: &IconMode) -> Option<String> {
+        let detail = self
+            .0
+            .detail()
+            .map(|name| format!("{name} "))
+            .unwrap_or_default();
+        // Container hostnames are usually container ids, which say nothing
+        let (name, color_with) = match &self.2 {
+            Some(container) if is_hash(&self.1) => (container.clone(), container),
+            Some(container) => (format!("{container} ({})", self.1), container),
+            None => (self.1.clone(), &self.1),
+        };
         Some(
-            format!("[{} {}]", self.icon(mode), self.1)
+            format!("[{} {detail}{name}]", self.icon(mode))
                 .visible()
-                .colorize_with(&self.1)
+                .colorize_with(color_with)
                 .invisible()
                 .to_string(),
         )
     }
 }
 
+fn is_hash(host: &str) -> bool {
+    host.len() >= 12 && host.bytes().all(|c| c.is_ascii_hexdigit())
+}
+
 impl Pretty for User {
     fn pretty(&self, mode: &IconMode) -> Option<String> {
         Some(
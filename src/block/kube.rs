@@ -0,0 +1,270 @@
+use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _, file};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Files which tell that working tree is deployed to kubernetes
+const KUBE_FILES: [&str; 6] = [
+    "kustomization.yaml",
+    "kustomization.yml",
+    "Chart.yaml",
+    "helmfile.yaml",
+    "skaffold.yaml",
+    "Tiltfile",
+];
+
+/// Current kubernetes context and its namespace
+#[derive(Clone)]
+pub struct Kube {
+    pub(crate) context: String,
+    namespace: Option<String>,
+}
+
+pub type MaybeKube = Option<Kube>;
+
+/// Context entry from `contexts:` list of kubeconfig
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Context {
+    name: String,
+    namespace: Option<String>,
+}
+
+impl Extend for MaybeKube {
+    fn extend(self: Box<Self>) -> Box<dyn Pretty> {
+        self
+    }
+}
+
+/// Strips comment and quotes from a scalar value
+fn scalar(value: &str) -> &str {
+    let value = match value.find(" #") {
+        Some(comment) => value.get(..comment).unwrap_or(value),
+        None => value,
+    };
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|x| x.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|x| x.strip_suffix('\'')))
+        .unwrap_or(value)
+}
+
+/// Reads `current-context` and `contexts` from kubeconfig. This is not a YAML parser, it only
+/// understands block-style mappings and lists which kubectl writes
+fn parse_kubeconfig(text: &str, current: &mut Option<String>, contexts: &mut Vec<Context>) {
+    let mut section = "";
+    // Indentation of the current list item contents
+    let mut item_indent = 0;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let mut indent = line.len() - trimmed.len();
+        let mut content = trimmed;
+
+        if indent == 0 && !content.starts_with('-') {
+            let (key, value) = content.split_once(':').unwrap_or((content, ""));
+            section = key;
+            if key == "current-context" && current.is_none() {
+                *current = Some(scalar(value).to_owned()).filter(|x| !x.is_empty());
+            }
+            continue;
+        }
+        if section != "contexts" {
+            continue;
+        }
+
+        if let Some(rest) = content.strip_prefix("- ") {
+            contexts.push(Context::default());
+            indent += 2;
+            item_indent = indent;
+            content = rest.trim_start();
+        }
+        let (Some(entry), Some((key, value))) = (contexts.last_mut(), content.split_once(':'))
+        else {
+            continue;
+        };
+        match key {
+            "name" if indent == item_indent => scalar(value).clone_into(&mut entry.name),
+            "namespace" => entry.namespace = Some(scalar(value).to_owned()),
+            _ => {}
+        }
+    }
+}
+
+/// Kubeconfig files from `KUBECONFIG`, or the default one
+fn kubeconfigs() -> Vec<PathBuf> {
+    match env::var("KUBECONFIG") {
+        Ok(paths) if !paths.is_empty() => paths
+            .split(':')
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .collect(),
+        _ => vec![PathBuf::from(env::var("HOME").unwrap_or_default()).join(".kube/config")],
+    }
+}
+
+/// Whether the working tree has kubernetes files, up to `top`
+fn in_tree(work_dir: &Path, top: Option<&Path>) -> bool {
+    file::ancestors_until(work_dir, top)
+        .any(|dir| KUBE_FILES.iter().any(|name| file::exists(&dir.join(name))))
+}
+
+impl Kube {
+    /// Current context, if kubernetes is relevant for the working directory. Kubernetes files are
+    /// searched for up to `top`
+    #[must_use]
+    pub fn detect(work_dir: &Path, top: Option<&Path>) -> Option<Kube> {
+        match env::var("PS1_KUBE").as_deref() {
+            Ok("never") => return None,
+            Ok("always") => {}
+            _ if !in_tree(work_dir, top) => return None,
+            _ => {}
+        }
+
+        // Files are merged, and the first `current-context` wins, as kubectl does
+        let mut current = None;
+        let mut contexts = vec![];
+        for path in kubeconfigs() {
+            if let Ok(text) = fs::read_to_string(path) {
+                parse_kubeconfig(&text, &mut current, &mut contexts);
+            }
+        }
+        let context = current?;
+        let namespace = contexts
+            .into_iter()
+            .find(|x| x.name == context)
+            .and_then(|x| x.namespace);
+        Some(Kube { context, namespace })
+    }
+}
+
+impl From<&Environment> for MaybeKube {
+    fn from(env: &Environment) -> Self {
+        env.kube.clone()
+    }
+}
+
+impl Icon for Kube {
+    fn icon(&self, mode: &IconMode) -> &'static str {
+        use IconMode::*;
+        match mode {
+            Text => "kube",
+            Icons => "󰠳",
+            MinimalIcons => "⎈",
+        }
+    }
+}
+
+impl Pretty for MaybeKube {
+    fn pretty(&self, mode: &IconMode) -> Option<String> {
+        let kube = self.as_ref()?;
+        Some(
+            format!(
+                "{} {}/{}",
+                kube.icon(mode),
+                kube.context,
+                kube.namespace.as_deref().unwrap_or("default")
+            )
+            .boxed()
+            .visible()
+            .colorize_with(&kube.context)
+            .with_reset()
+            .invisible()
+            .to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Context, in_tree, parse_kubeconfig};
+    use crate::probe::fixture::Fixture;
+    use std::path::PathBuf;
+
+    #[test]
+    fn kubeconfig() {
+        let first = "\
+apiVersion: v1
+clusters:
+- cluster:
+    server: https://prod.example.com:6443
+  name: prod
+contexts:
+- context:
+    cluster: prod
+    namespace: payments # team namespace
+    user: admin
+  name: prod-admin
+- name: \"dev\"
+  context:
+    cluster: dev
+    user: dev
+current-context: 'prod-admin'
+kind: Config
+";
+        let second = "\
+contexts:
+  - context:
+      namespace: sandbox
+    name: kind-local
+current-context: kind-local
+";
+        let mut current = None;
+        let mut contexts = vec![];
+        parse_kubeconfig(first, &mut current, &mut contexts);
+        parse_kubeconfig(second, &mut current, &mut contexts);
+        assert_eq!(
+            current.as_deref(),
+            Some("prod-admin"),
+            "first current-context wins"
+        );
+        assert_eq!(
+            contexts,
+            [
+                Context {
+                    name: "prod-admin".to_owned(),
+                    namespace: Some("payments".to_owned()),
+                },
+                Context {
+                    name: "dev".to_owned(),
+                    namespace: None,
+                },
+                Context {
+                    name: "kind-local".to_owned(),
+                    namespace: Some("sandbox".to_owned()),
+                },
+            ],
+            "contexts are merged in order"
+        );
+    }
+
+    #[test]
+    fn gating() {
+        let fixture = Fixture::new(&[
+            ("/home/yuki/.kube/config", ""),
+            ("/home/yuki/notes/", ""),
+            ("/home/yuki/deploy/Chart.yaml", ""),
+            ("/home/yuki/deploy/templates/", ""),
+            ("/srv/kustomization.yaml", ""),
+            ("/srv/repo/app/", ""),
+        ]);
+        let probe = fixture.probe();
+        let home = probe.path("/home/yuki");
+        for (work_dir, top, expected) in [
+            ("/home/yuki/notes", Some(&home), false),
+            ("/home/yuki", Some(&home), false),
+            ("/home/yuki/deploy/templates", Some(&home), true),
+            ("/srv/repo/app", Some(&probe.path("/srv/repo")), false),
+            ("/srv/repo/app", None, true),
+        ] {
+            assert_eq!(
+                in_tree(&probe.path(work_dir), top.map(PathBuf::as_path)),
+                expected,
+                "{work_dir}"
+            );
+        }
+    }
+}
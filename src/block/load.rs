@@ -0,0 +1,190 @@
+use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _};
+use std::{env, fs, thread};
+
+/// Pressure stall information resource, see `/proc/pressure`
+#[derive(Clone, Copy)]
+enum Pressure {
+    Cpu,
+    Memory,
+    Io,
+}
+
+impl Pressure {
+    fn path(self) -> &'static str {
+        match self {
+            Self::Cpu => "/proc/pressure/cpu",
+            Self::Memory => "/proc/pressure/memory",
+            Self::Io => "/proc/pressure/io",
+        }
+    }
+
+    /// Reads `avg10` from the "some" line, which is the share of the last ten seconds in which at
+    /// least one task was stalled on the resource
+    fn avg10(self) -> Option<f64> {
+        some_avg10(&fs::read_to_string(self.path()).ok()?)
+    }
+}
+
+/// Parses `avg10` of the "some" line of a pressure file
+fn some_avg10(psi: &str) -> Option<f64> {
+    psi.lines()
+        .find_map(|line| line.strip_prefix("some "))?
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("avg10="))?
+        .parse()
+        .ok()
+}
+
+impl Icon for Pressure {
+    fn icon(&self, mode: &IconMode) -> &'static str {
+        use IconMode::*;
+        match self {
+            Self::Cpu => match mode {
+                Text => "cpu ",
+                Icons | MinimalIcons => " ",
+            },
+            Self::Memory => match mode {
+                Text => "mem ",
+                Icons | MinimalIcons => "󰍛 ",
+            },
+            Self::Io => match mode {
+                Text => "io ",
+                Icons | MinimalIcons => "󰋊 ",
+            },
+        }
+    }
+}
+
+pub struct Load {
+    /// One-minute load average per CPU, if over threshold
+    per_cpu: Option<f64>,
+    /// Stall percentages over threshold
+    pressure: Vec<(Pressure, f64)>,
+    /// Whether any value is twice over its threshold
+    critical: bool,
+}
+
+impl Extend for Load {
+    fn extend(self: Box<Self>) -> Box<dyn Pretty> {
+        self
+    }
+}
+
+fn threshold_from_env(var: &str, default: f64) -> f64 {
+    env::var(var)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+fn load_per_cpu() -> Option<f64> {
+    let load: f64 = fs::read_to_string("/proc/loadavg")
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    let cpus = thread::available_parallelism()
+        .ok()
+        .and_then(|n| u32::try_from(n.get()).ok())
+        .unwrap_or(1);
+    Some(load / f64::from(cpus))
+}
+
+impl From<&Environment> for Load {
+    fn from(_: &Environment) -> Self {
+        let load_threshold = threshold_from_env("PS1_LOAD_THRESHOLD", 1.0);
+        let psi_threshold = threshold_from_env("PS1_PSI_THRESHOLD", 10.0);
+
+        let per_cpu = load_per_cpu().filter(|&load| load > load_threshold);
+        let pressure = [Pressure::Cpu, Pressure::Memory, Pressure::Io]
+            .into_iter()
+            .filter_map(|kind| Some((kind, kind.avg10()?)))
+            .filter(|&(_, avg)| avg > psi_threshold)
+            .collect::<Vec<_>>();
+        let critical = per_cpu.is_some_and(|load| load > 2.0_f64 * load_threshold)
+            || pressure
+                .iter()
+                .any(|&(_, avg)| avg > 2.0_f64 * psi_threshold);
+
+        Load {
+            per_cpu,
+            pressure,
+            critical,
+        }
+    }
+}
+
+impl Icon for Load {
+    fn icon(&self, mode: &IconMode) -> &'static str {
+        use IconMode::*;
+        match mode {
+            Text => "load ",
+            Icons | MinimalIcons => "󰓅 ",
+        }
+    }
+}
+
+impl Pretty for Load {
+    fn pretty(&self, mode: &IconMode) -> Option<String> {
+        let parts = self
+            .per_cpu
+            .map(|load| format!("{}{load:.2}", self.icon(mode)))
+            .into_iter()
+            .chain(
+                self.pressure
+                    .iter()
+                    .map(|(kind, avg)| format!("{}{avg:.0}%", kind.icon(mode))),
+            )
+            .collect::<Vec<_>>();
+
+        if parts.is_empty() {
+            None?;
+        }
+
+        let text = parts.join(" ");
+        let text = text.boxed();
+        let text = text.visible();
+        Some(
+            if self.critical {
+                text.light_red()
+            } else {
+                text.yellow()
+            }
+            .with_reset()
+            .invisible()
+            .to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::some_avg10;
+
+    #[test]
+    fn pressure() {
+        for (psi, expected) in [
+            (
+                "some avg10=12.50 avg60=3.00 avg300=1.00 total=123\n\
+                 full avg10=4.00 avg60=1.00 avg300=0.50 total=45\n",
+                Some(12.5_f64),
+            ),
+            (
+                "full avg10=4.00 avg60=1.00 avg300=0.50 total=45\n\
+                 some avg60=3.00 avg10=0.25 avg300=1.00 total=123\n",
+                Some(0.25_f64),
+            ),
+            // CPU pressure has no "full" line on older kernels
+            (
+                "some avg10=0.00 avg60=0.00 avg300=0.00 total=0\n",
+                Some(0.0_f64),
+            ),
+            ("full avg10=4.00 avg60=1.00 avg300=0.50 total=45\n", None),
+            ("some avg10=high\n", None),
+            ("", None),
+        ] {
+            assert_eq!(some_avg10(psi), expected, "{psi:?}");
+        }
+    }
+}
@@ -1,10 +1,68 @@
 use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _};
 use rustix::process::Signal;
+use std::env;
+
+/// Names of sysexits.h codes, starting from `EX_USAGE` = 64
+const SYSEXITS: [&str; 15] = [
+    "USAGE",
+    "DATAERR",
+    "NOINPUT",
+    "NOUSER",
+    "NOHOST",
+    "UNAVAILABLE",
+    "SOFTWARE",
+    "OSERR",
+    "OSFILE",
+    "CANTCREAT",
+    "IOERR",
+    "TEMPFAIL",
+    "PROTOCOL",
+    "NOPERM",
+    "CONFIG",
+];
+
+/// Exit code with a well-known meaning
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Meaning {
+    /// 126, found but could not be executed
+    NotExecutable,
+    /// 127, command not found
+    NotFound,
+    /// 130, interrupted with Ctrl-C
+    Interrupted,
+    /// 64 to 78, from sysexits.h
+    SysExit(&'static str),
+}
+
+impl Meaning {
+    fn from_code(code: u8) -> Option<Meaning> {
+        Some(match code {
+            126 => Self::NotExecutable,
+            127 => Self::NotFound,
+            130 => Self::Interrupted,
+            _ => Self::SysExit(SYSEXITS.get(usize::from(code.checked_sub(64)?))?),
+        })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::NotExecutable => "NOEXEC",
+            Self::NotFound => "NOTFOUND",
+            Self::Interrupted => "INT",
+            Self::SysExit(name) => name,
+        }
+    }
+}
 
 pub enum ReturnCode {
     Ok,
     Failed(u8),
-    Signaled(String),
+    /// Well-known failure, with the code if it should be shown too
+    Named(Meaning, Option<u8>),
+    Signaled(String, Option<u8>),
+    /// Pipeline where some command failed differently than the last one, and whether the whole
+    /// pipeline failed
+    Pipeline(Vec<String>, bool),
     NotAvailable,
 }
 
@@ -16,13 +74,31 @@ impl Extend for ReturnCode {
 
 impl From<&Environment> for ReturnCode {
     fn from(args: &Environment) -> Self {
+        let numeric = env::var("PS1_RETURN_CODE").is_ok_and(|mode| mode == "numeric");
+        if let Some((last, stages)) = args.pipe_status.split_last()
+            && stages.iter().any(|code| code != last)
+        {
+            return Self::Pipeline(
+                args.pipe_status
+                    .iter()
+                    .map(|&code| signal_name(code.wrapping_sub(128)).unwrap_or(code.to_string()))
+                    .collect(),
+                args.ret_code.is_some_and(|code| code != 0),
+            );
+        }
         match args.ret_code {
             Some(0) => Self::Ok,
             None => Self::NotAvailable,
-            Some(code) => match signal_name(code.wrapping_sub(128)) {
-                Some(sig) => Self::Signaled(sig),
-                None => Self::Failed(code),
-            },
+            Some(code) => {
+                let shown = numeric.then_some(code);
+                if let Some(meaning) = Meaning::from_code(code) {
+                    Self::Named(meaning, shown)
+                } else if let Some(sig) = signal_name(code.wrapping_sub(128)) {
+                    Self::Signaled(sig, shown)
+                } else {
+                    Self::Failed(code)
+                }
+            }
         }
     }
 }
@@ -35,7 +111,8 @@ impl Icon for ReturnCode {
                 Icons => "✓",
                 Text | MinimalIcons => "",
             },
-            Self::Failed(..) => "",
+            Self::Failed(..) | Self::Pipeline(..) => "",
+            Self::Named(meaning, _) => meaning.icon(mode),
             Self::Signaled(..) => match &mode {
                 Icons => "󰜃 ",
                 Text | MinimalIcons => "",
@@ -48,15 +125,31 @@ impl Icon for ReturnCode {
     }
 }
 
+impl Icon for Meaning {
+    fn icon(&self, mode: &IconMode) -> &'static str {
+        use IconMode::*;
+        match (self, mode) {
+            (Self::NotExecutable, Text) => "x ",
+            (Self::NotExecutable, Icons | MinimalIcons) => " ",
+            (Self::NotFound, Text | MinimalIcons) => "? ",
+            (Self::NotFound, Icons) => " ",
+            (Self::Interrupted, Text | MinimalIcons) => "^C ",
+            (Self::Interrupted, Icons) => " ",
+            (Self::SysExit(..), Text) => "EX_",
+            (Self::SysExit(..), Icons | MinimalIcons) => " ",
+        }
+    }
+}
+
 impl Pretty for ReturnCode {
     fn pretty(&self, mode: &IconMode) -> Option<String> {
         let icon = self.icon(mode);
         let text = match &self {
             Self::Ok | Self::NotAvailable => icon.into(),
-            // 126 not exec
-            // 127 not found
             Self::Failed(code) => format!("{code}{icon}"),
-            Self::Signaled(sig) => format!("{icon}{sig}"),
+            Self::Named(meaning, code) => with_code(format!("{icon}{}", meaning.name()), *code),
+            Self::Signaled(sig, code) => with_code(format!("{icon}{sig}"), *code),
+            Self::Pipeline(stages, _) => stages.join("|"),
         };
         if text.is_empty() {
             None?;
@@ -66,8 +159,12 @@ impl Pretty for ReturnCode {
         Some(
             match &self {
                 Self::Ok => text.light_green(),
-                Self::Failed(..) => text.light_red(),
-                Self::Signaled(..) => text.true_color(255, 170, 0),
+                // Interrupt is a signal too
+                Self::Named(Meaning::Interrupted, _) | Self::Signaled(..) => {
+                    text.true_color(255, 170, 0)
+                }
+                Self::Failed(..) | Self::Named(..) | Self::Pipeline(_, true) => text.light_red(),
+                Self::Pipeline(_, false) => text.yellow(),
                 Self::NotAvailable => text.light_gray(),
             }
             .with_reset()
@@ -77,6 +174,13 @@ impl Pretty for ReturnCode {
     }
 }
 
+fn with_code(name: String, code: Option<u8>) -> String {
+    match code {
+        Some(code) => format!("{name} {code}"),
+        None => name,
+    }
+}
+
 fn signal_name(sig: u8) -> Option<String> {
     let sig = i32::from(sig);
     if let Some(sig) = Signal::from_raw(sig) {
@@ -87,3 +191,80 @@ fn signal_name(sig: u8) -> Option<String> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Meaning, ReturnCode};
+    use crate::{Chassis, Environment};
+    use std::path::PathBuf;
+
+    fn return_code(ret_code: u8, pipe_status: &[u8]) -> ReturnCode {
+        ReturnCode::from(&Environment {
+            ret_code: Some(ret_code),
+            pipe_status: pipe_status.to_vec(),
+            jobs_count: 0,
+            elapsed_time: None,
+            work_dir: PathBuf::from("/"),
+            git_tree: None,
+            user: "yuki".to_owned(),
+            host: "gensokyo".to_owned(),
+            chassis: Chassis::Unknown,
+            current_home: None,
+            kube: None,
+            tier: None,
+        })
+    }
+
+    #[test]
+    fn meanings() {
+        for (code, expected) in [
+            (1, None),
+            (63, None),
+            (64, Some("USAGE")),
+            (77, Some("NOPERM")),
+            (78, Some("CONFIG")),
+            (79, None),
+            (126, Some("NOEXEC")),
+            (127, Some("NOTFOUND")),
+            (130, Some("INT")),
+            (137, None),
+        ] {
+            assert_eq!(
+                Meaning::from_code(code).map(Meaning::name),
+                expected,
+                "{code}"
+            );
+        }
+    }
+
+    #[test]
+    fn pipelines() {
+        for (ret_code, pipe_status, expected) in [
+            (0, &[0, 1, 0][..], Some(("0|1|0", false))),
+            (0, &[141, 0], Some(("PIPE|0", false))),
+            (1, &[0, 1], Some(("0|1", true))),
+            (1, &[1, 1], None),
+            (1, &[1], None),
+        ] {
+            let pipeline = match return_code(ret_code, pipe_status) {
+                ReturnCode::Pipeline(stages, failed) => Some((stages.join("|"), failed)),
+                _ => None,
+            };
+            assert_eq!(
+                pipeline
+                    .as_ref()
+                    .map(|(stages, failed)| (stages.as_str(), *failed)),
+                expected,
+                "{pipe_status:?}"
+            );
+        }
+        assert!(
+            matches!(return_code(1, &[1, 1]), ReturnCode::Failed(1)),
+            "equal statuses fall back to the last one"
+        );
+        assert!(
+            matches!(return_code(1, &[1]), ReturnCode::Failed(1)),
+            "single command is not a pipeline"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{Environment, Extend, IconMode, Pretty, Style as _, targets};
+use std::env;
+
+/// Runnable targets for the extended line, up to `PS1_TARGETS` per tool
+pub struct Targets {
+    sources: Vec<targets::Source>,
+    limit: usize,
+}
+
+impl Extend for Targets {
+    fn extend(self: Box<Self>) -> Box<dyn Pretty> {
+        self
+    }
+}
+
+impl From<&Environment> for Targets {
+    fn from(env: &Environment) -> Self {
+        let limit = env::var("PS1_TARGETS")
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or(0);
+        let sources = if limit == 0 {
+            vec![]
+        } else {
+            targets::find(&env.work_dir)
+        };
+        Targets { sources, limit }
+    }
+}
+
+impl Pretty for Targets {
+    fn pretty(&self, _: &IconMode) -> Option<String> {
+        let boxes = self
+            .sources
+            .iter()
+            .filter(|source| !source.targets.is_empty())
+            .map(|source| {
+                let shown = source.targets.iter().take(self.limit);
+                let hidden = source.targets.len().saturating_sub(self.limit);
+                let more = (hidden != 0).then(|| format!("+{hidden}"));
+                [source.tool]
+                    .into_iter()
+                    .chain(shown.map(String::as_str))
+                    .chain(more.as_deref())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .boxed()
+                    .visible()
+                    .purple()
+                    .with_reset()
+                    .invisible()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        (!boxes.is_empty()).then(|| boxes.join(" "))
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{
+    Environment, Extend, Icon, IconMode, Pretty, Probe, Style as _, block::kube::Kube, chassis,
+    glob,
+};
+use std::{env, fs};
+
+/// Marker file with the tier name, for hosts without `DEPLOYMENT=` in `/etc/machine-info`
+const MARKER: &str = "/etc/statusline-tier";
+
+/// Deployment tier of the host, to tell production from anything else at a glance
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Tier {
+    Production,
+    Staging,
+    Development,
+    /// Some other tier, with its name uppercased
+    Other(String),
+}
+
+pub type MaybeTier = Option<Tier>;
+
+impl Tier {
+    /// Parses tier name, as in systemd's `DEPLOYMENT=`, or one of common abbreviations
+    fn from_name(name: &str) -> Option<Tier> {
+        let name = name.trim();
+        Some(match name.to_ascii_lowercase().as_str() {
+            "" => None?,
+            "production" | "prod" | "prd" | "live" => Tier::Production,
+            "staging" | "stage" | "stg" | "integration" | "preprod" => Tier::Staging,
+            "development" | "dev" | "test" | "testing" => Tier::Development,
+            _ => Tier::Other(name.to_uppercase()),
+        })
+    }
+
+    /// Tier of the first `PS1_TIER_RULES` pattern matching the name
+    pub(crate) fn matching(name: &str) -> Option<Tier> {
+        Tier::matching_rules(&env::var("PS1_TIER_RULES").ok()?, name)
+    }
+
+    /// Tier of the first rule like `*.prod.example.com=prod` whose pattern matches the name
+    fn matching_rules(rules: &str, name: &str) -> Option<Tier> {
+        rules
+            .split_whitespace()
+            .filter_map(|rule| rule.rsplit_once('='))
+            .find(|(pattern, _)| glob::matches(pattern, name))
+            .and_then(|(_, tier)| Tier::from_name(tier))
+    }
+
+    /// Tier of the host alone, from the first of its sources which has one
+    fn of_host(
+        probe: &Probe,
+        host: &str,
+        matching: impl FnOnce(&str) -> Option<Tier>,
+    ) -> Option<Tier> {
+        None.or_else(|| {
+            Tier::from_name(
+                fs::read_to_string(probe.path(MARKER))
+                    .ok()?
+                    .lines()
+                    .next()?,
+            )
+        })
+        .or_else(|| Tier::from_name(&chassis::machine_info(probe, "DEPLOYMENT")?))
+        .or_else(|| matching(host))
+    }
+
+    /// Tier of the host from marker file, `DEPLOYMENT=` in `/etc/machine-info`, or hostname
+    /// rules, in order. If there is a kubernetes context, the more severe of its tier by the same
+    /// rules and the host's one wins
+    #[must_use]
+    pub fn detect(host: &str, kube: Option<&Kube>) -> Option<Tier> {
+        let host = Tier::of_host(&Probe::default(), host, Tier::matching);
+        let kube = kube.and_then(|kube| Tier::matching(&kube.context));
+        host.into_iter().chain(kube).max_by_key(Tier::severity)
+    }
+
+    fn severity(&self) -> u8 {
+        match self {
+            Tier::Development => 0,
+            Tier::Other(_) => 1,
+            Tier::Staging => 2,
+            Tier::Production => 3,
+        }
+    }
+
+    /// Short uppercase name
+    pub(crate) fn label(&self) -> &str {
+        match self {
+            Tier::Production => "PROD",
+            Tier::Staging => "STAGING",
+            Tier::Development => "DEV",
+            Tier::Other(name) => name,
+        }
+    }
+}
+
+impl Extend for MaybeTier {
+    fn extend(self: Box<Self>) -> Box<dyn Pretty> {
+        self
+    }
+}
+
+impl From<&Environment> for MaybeTier {
+    fn from(env: &Environment) -> Self {
+        env.tier.clone()
+    }
+}
+
+impl Icon for Tier {
+    fn icon(&self, mode: &IconMode) -> &'static str {
+        use IconMode::*;
+        match mode {
+            Text => "",
+            Icons | MinimalIcons => match self {
+                Tier::Production => " ",
+                Tier::Staging => " ",
+                Tier::Development => " ",
+                Tier::Other(_) => " ",
+            },
+        }
+    }
+}
+
+impl Pretty for MaybeTier {
+    fn pretty(&self, mode: &IconMode) -> Option<String> {
+        let tier = self.as_ref()?;
+        let text = format!(" {}{} ", tier.icon(mode), tier.label());
+        let text = text.visible();
+        let text = text.inverse();
+        let text = text.bold();
+        Some(
+            match tier {
+                Tier::Production => text.red(),
+                Tier::Staging => text.yellow(),
+                Tier::Development => text.green(),
+                Tier::Other(_) => text.cyan(),
+            }
+            .with_reset()
+            .invisible()
+            .to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tier;
+    use crate::probe::fixture::Fixture;
+
+    #[test]
+    fn names() {
+        for (name, expected) in [
+            ("production", Some(Tier::Production)),
+            (" Prod\n", Some(Tier::Production)),
+            ("LIVE", Some(Tier::Production)),
+            ("stg", Some(Tier::Staging)),
+            ("preprod", Some(Tier::Staging)),
+            ("testing", Some(Tier::Development)),
+            ("dev", Some(Tier::Development)),
+            ("qa", Some(Tier::Other("QA".to_owned()))),
+            ("", None),
+            ("  ", None),
+        ] {
+            assert_eq!(Tier::from_name(name), expected, "{name:?}");
+        }
+    }
+
+    #[test]
+    fn rules() {
+        let rules = "*.prod.example.com=prod db-??=staging *=dev";
+        for (name, expected) in [
+            ("api.prod.example.com", Some(Tier::Production)),
+            ("db-01", Some(Tier::Staging)),
+            ("db-001", Some(Tier::Development)),
+            ("laptop", Some(Tier::Development)),
+        ] {
+            assert_eq!(Tier::matching_rules(rules, name), expected, "{name}");
+        }
+        assert_eq!(
+            Tier::matching_rules("db-*=prod", "web-01"),
+            None,
+            "no rule matches"
+        );
+        assert_eq!(
+            Tier::matching_rules("a=b=qa", "a=b"),
+            Some(Tier::Other("QA".to_owned())),
+            "tier is after the last equals sign"
+        );
+    }
+
+    #[test]
+    fn priority() {
+        let rules = |host: &str| Tier::matching_rules("*=dev", host);
+        for (files, expected) in [
+            (
+                &[
+                    ("/etc/statusline-tier", "prod\n"),
+                    ("/etc/machine-info", "DEPLOYMENT=staging\n"),
+                ][..],
+                Some(Tier::Production),
+            ),
+            (
+                &[
+                    ("/etc/statusline-tier", "\n"),
+                    ("/etc/machine-info", "DEPLOYMENT=\"staging\"\n"),
+                ],
+                Some(Tier::Staging),
+            ),
+            (
+                &[("/etc/machine-info", "PRETTY_HOSTNAME=box\n")],
+                Some(Tier::Development),
+            ),
+        ] {
+            let fixture = Fixture::new(files);
+            assert_eq!(
+                Tier::of_host(&fixture.probe(), "box", rules),
+                expected,
+                "{files:?}"
+            );
+        }
+        let fixture = Fixture::new(&[]);
+        assert_eq!(
+            Tier::of_host(&fixture.probe(), "box", |_| None),
+            None,
+            "no source"
+        );
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _, file, ini, json::Json};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Host triple parts which are dropped from rustup toolchain names
+const HOST_ARCHES: [&str; 8] = [
+    "-x86_64",
+    "-aarch64",
+    "-i686",
+    "-arm",
+    "-riscv64",
+    "-powerpc",
+    "-s390x",
+    "-loongarch64",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Rust,
+    Node,
+    Go,
+    Python,
+    Java,
+}
+
+impl Language {
+    /// Files which tell that the project is written in this language
+    fn markers(self) -> &'static [&'static str] {
+        match self {
+            Language::Rust => &["Cargo.toml"],
+            Language::Node => &["package.json"],
+            Language::Go => &["go.mod"],
+            Language::Python => &[
+                "pyproject.toml",
+                "setup.py",
+                "setup.cfg",
+                "requirements.txt",
+            ],
+            Language::Java => &["pom.xml", "build.gradle", "build.gradle.kts"],
+        }
+    }
+
+    /// Tool name in `.tool-versions`
+    fn asdf_name(self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::Node => "nodejs",
+            Language::Go => "golang",
+            Language::Python => "python",
+            Language::Java => "java",
+        }
+    }
+}
+
+/// Toolchain versions of the project in working directory, as pinned by version files
+pub struct Toolchain(Vec<(Language, String)>);
+
+impl Extend for Toolchain {
+    fn extend(self: Box<Self>) -> Box<dyn Pretty> {
+        self
+    }
+}
+
+/// Directories searched for version files, nearest first
+struct Tree<'a>(Vec<&'a Path>);
+
+impl<'a> Tree<'a> {
+    /// Working directory and its parents up to the repository, or up to the home directory. Files
+    /// right in home are global defaults of pyenv, asdf and the like, not project ones
+    fn new(env: &'a Environment) -> Self {
+        let home = env.current_home.as_ref().map(|(home, _)| home.as_path());
+        Tree(
+            file::ancestors_until(&env.work_dir, env.project_top())
+                .filter(|dir| Some(*dir) != home)
+                .collect(),
+        )
+    }
+
+    /// Nearest file with this name
+    fn find(&self, name: &str) -> Option<PathBuf> {
+        self.0
+            .iter()
+            .map(|dir| dir.join(name))
+            .find(file::points_to_file)
+    }
+
+    /// Reads the nearest file with this name, first line only
+    fn first_line(&self, name: &str) -> Option<String> {
+        let text = file::read_small(&self.find(name)?)?;
+        Some(text.lines().next()?.trim().to_owned()).filter(|line| !line.is_empty())
+    }
+}
+
+/// Strips TOML quotes
+fn unquote(value: &str) -> &str {
+    value.trim_matches(['"', '\''])
+}
+
+/// Shortens rustup toolchain name, `stable-x86_64-unknown-linux-gnu` to `stable`
+fn short_toolchain(name: &str) -> &str {
+    HOST_ARCHES
+        .iter()
+        .filter_map(|arch| name.find(arch))
+        .min()
+        .and_then(|end| name.get(..end))
+        .unwrap_or(name)
+}
+
+/// Channel from `rust-toolchain.toml`, or the legacy `rust-toolchain` if that one can not be read
+fn pinned_rust(tree: &Tree) -> Option<String> {
+    tree.find("rust-toolchain.toml")
+        .and_then(|path| file::read_small(&path))
+        .and_then(|text| ini::value(&text, "toolchain", "channel"))
+        .map(|channel| unquote(&channel).to_owned())
+        .or_else(|| tree.first_line("rust-toolchain"))
+}
+
+fn rust(tree: &Tree) -> Option<String> {
+    tree.find("Cargo.toml")?;
+    if let Ok(toolchain) = env::var("RUSTUP_TOOLCHAIN") {
+        return Some(short_toolchain(&toolchain).to_owned());
+    }
+    if let Some(channel) = pinned_rust(tree) {
+        return Some(channel);
+    }
+    let rustup = env::var("RUSTUP_HOME").map_or_else(
+        |_| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".rustup"),
+        PathBuf::from,
+    );
+    let settings = fs::read_to_string(rustup.join("settings.toml")).ok()?;
+    let toolchain = ini::value(&settings, "", "default_toolchain")?;
+    Some(short_toolchain(unquote(&toolchain)).to_owned())
+}
+
+fn node(tree: &Tree) -> Option<String> {
+    let version = tree
+        .first_line(".nvmrc")
+        .or_else(|| tree.first_line(".node-version"))
+        .or_else(|| {
+            let package = file::read_small(&tree.find("package.json")?)?;
+            Some(
+                Json::parse(&package)?
+                    .get("engines")?
+                    .get("node")?
+                    .as_str()?
+                    .to_owned(),
+            )
+        })?;
+    Some(version.trim_start_matches('v').to_owned())
+}
+
+fn go(tree: &Tree) -> Option<String> {
+    let module = file::read_small(&tree.find("go.mod")?)?;
+    module
+        .lines()
+        .find_map(|line| Some(line.trim().strip_prefix("go ")?.trim().to_owned()))
+}
+
+/// Version from asdf or mise `.tool-versions`, for projects in this language only
+fn asdf(tree: &Tree, language: Language) -> Option<String> {
+    language
+        .markers()
+        .iter()
+        .find_map(|marker| tree.find(marker))?;
+    let versions = file::read_small(&tree.find(".tool-versions")?)?;
+    versions.lines().find_map(|line| {
+        let mut words = line.split_whitespace();
+        (words.next()? == language.asdf_name()).then_some(words.next()?.to_owned())
+    })
+}
+
+impl From<&Environment> for Toolchain {
+    fn from(env: &Environment) -> Self {
+        let tree = Tree::new(env);
+        Toolchain(
+            [
+                (Language::Rust, rust(&tree)),
+                (Language::Node, node(&tree)),
+                (Language::Go, go(&tree)),
+                (Language::Python, tree.first_line(".python-version")),
+                (Language::Java, tree.first_line(".java-version")),
+            ]
+            .into_iter()
+            .filter_map(|(language, version)| {
+                Some((language, version.or_else(|| asdf(&tree, language))?))
+            })
+            .collect(),
+        )
+    }
+}
+
+impl Icon for Language {
+    fn icon(&self, mode: &IconMode) -> &'static str {
+        use IconMode::*;
+        match (mode, self) {
+            (Text, Language::Rust) => "rs",
+            (Text, Language::Node) => "node",
+            (Text, Language::Go) => "go",
+            (Text, Language::Python) => "py",
+            (Text, Language::Java) => "java",
+            (Icons | MinimalIcons, Language::Rust) => "",
+            (Icons | MinimalIcons, Language::Node) => "",
+            (Icons | MinimalIcons, Language::Go) => "",
+            (Icons | MinimalIcons, Language::Python) => "",
+            (Icons | MinimalIcons, Language::Java) => "",
+        }
+    }
+}
+
+impl Pretty for Toolchain {
+    fn pretty(&self, mode: &IconMode) -> Option<String> {
+        let Self(toolchains) = &self;
+        if toolchains.is_empty() {
+            None?;
+        }
+        Some(
+            toolchains
+                .iter()
+                .map(|(language, version)| format!("{} {version}", language.icon(mode)))
+                .collect::<Vec<_>>()
+                .join(" ")
+                .boxed()
+                .visible()
+                .cyan()
+                .with_reset()
+                .invisible()
+                .to_string(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Language, Toolchain, Tree, asdf, pinned_rust, short_toolchain};
+    use crate::{Chassis, Environment, probe::fixture::Fixture};
+    use std::path::{Path, PathBuf};
+
+    fn environment(work_dir: PathBuf, git_tree: Option<PathBuf>, home: &Path) -> Environment {
+        Environment {
+            ret_code: None,
+            pipe_status: vec![],
+            jobs_count: 0,
+            elapsed_time: None,
+            work_dir,
+            git_tree,
+            user: "yuki".to_owned(),
+            host: "gensokyo".to_owned(),
+            chassis: Chassis::Unknown,
+            current_home: Some((home.to_path_buf(), String::new())),
+            kube: None,
+            tier: None,
+        }
+    }
+
+    #[test]
+    fn toolchain_names() {
+        assert_eq!(
+            short_toolchain("stable-x86_64-unknown-linux-gnu"),
+            "stable",
+            "host is dropped"
+        );
+        assert_eq!(
+            short_toolchain("nightly-2024-05-01-aarch64-apple-darwin"),
+            "nightly-2024-05-01",
+            "date is kept"
+        );
+        assert_eq!(short_toolchain("1.80.0"), "1.80.0", "plain version");
+    }
+
+    #[test]
+    fn search_bounds() {
+        let huge = format!("[toolchain]\nchannel = \"beta\"\n{}", "#".repeat(2 << 20));
+        let fixture = Fixture::new(&[
+            ("/home/yuki/.python-version", "3.12.1\n"),
+            ("/home/yuki/.tool-versions", "python 3.10.0\n"),
+            ("/home/yuki/notes/", ""),
+            ("/home/yuki/app/pyproject.toml", ""),
+            (
+                "/home/yuki/app/.tool-versions",
+                "nodejs 20.1.0\npython 3.11.4\n",
+            ),
+            ("/home/yuki/app/src/", ""),
+            ("/home/yuki/work/.java-version", "21\n"),
+            ("/home/yuki/work/repo/.git/", ""),
+            ("/home/yuki/work/repo/lib/", ""),
+            ("/home/yuki/work/repo/lib/rust-toolchain.toml", &huge),
+            ("/home/yuki/work/repo/rust-toolchain", "1.80.0\n"),
+        ]);
+        let probe = fixture.probe();
+        let home = probe.path("/home/yuki");
+        let languages = |env: &Environment| {
+            let Toolchain(versions) = Toolchain::from(env);
+            versions
+                .into_iter()
+                .map(|(language, version)| (language.asdf_name(), version))
+                .collect::<Vec<_>>()
+        };
+
+        let notes = environment(probe.path("/home/yuki/notes"), None, &home);
+        assert_eq!(languages(&notes), [], "global version files are ignored");
+
+        let app = environment(probe.path("/home/yuki/app/src"), None, &home);
+        assert_eq!(
+            languages(&app),
+            [("python", "3.11.4".to_owned())],
+            "asdf for languages with project files only"
+        );
+        assert_eq!(
+            asdf(&Tree::new(&app), Language::Node),
+            None,
+            "no package.json"
+        );
+
+        let repo = environment(
+            probe.path("/home/yuki/work/repo/lib"),
+            Some(probe.path("/home/yuki/work/repo")),
+            &home,
+        );
+        let tree = Tree::new(&repo);
+        assert_eq!(
+            pinned_rust(&tree).as_deref(),
+            Some("1.80.0"),
+            "unreadable rust-toolchain.toml falls through"
+        );
+        assert_eq!(
+            tree.first_line(".java-version"),
+            None,
+            "above the repository"
+        );
+    }
+}
@@ -1,16 +1,47 @@
-use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _};
+use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _, file};
 use anyhow::Result;
 use std::{
     env,
     ffi::OsStr,
-    fs::File,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs::{self, File},
     io::{BufRead as _, BufReader},
     path::{Path, PathBuf},
 };
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Manager {
+    Venv,
+    Uv,
+    Poetry,
+    Pipenv,
+    Conda,
+    Pyenv,
+}
+
+impl Display for Manager {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match *self {
+                Self::Venv => "venv",
+                Self::Uv => "uv",
+                Self::Poetry => "poetry",
+                Self::Pipenv => "pipenv",
+                Self::Conda => "conda",
+                Self::Pyenv => "pyenv",
+            }
+        )
+    }
+}
+
 pub struct Venv {
     name: String,
     version: String,
+    manager: Manager,
+    /// Project environment exists, but is not activated
+    inactive: bool,
 }
 
 pub type MaybeVenv = Option<Venv>;
@@ -22,28 +53,40 @@ impl Extend for MaybeVenv {
 }
 
 impl From<&Environment> for MaybeVenv {
-    fn from(_: &Environment) -> Self {
-        let path = PathBuf::from(env::var("VIRTUAL_ENV").ok()?);
-        let name = venv_name(&path).to_owned();
-        let version = venv_ver(&path)
-            .unwrap_or_default()
-            .unwrap_or("<sys?>".to_owned());
-
-        Some(Venv { name, version })
+    fn from(env: &Environment) -> Self {
+        None.or_else(virtual_env)
+            .or_else(conda)
+            .or_else(|| project_venv(&env.work_dir))
+            .or_else(pyenv)
     }
 }
 
 impl Pretty for MaybeVenv {
     fn pretty(&self, mode: &IconMode) -> Option<String> {
-        self.as_ref().map(|venv| {
-            format!("{} {}|{}", venv.icon(mode), venv.version, venv.name)
-                .boxed()
-                .visible()
-                .yellow()
-                .with_reset()
-                .invisible()
-                .to_string()
-        })
+        let venv = self.as_ref()?;
+        let label = match venv.manager {
+            Manager::Venv => venv.name.clone(),
+            manager if venv.name == venv.version => manager.to_string(),
+            manager => format!("{manager}:{}", venv.name),
+        };
+        let text = format!("{} {}|{label}", venv.icon(mode), venv.version);
+        let text = if venv.inactive {
+            format!("{text} inactive")
+        } else {
+            text
+        };
+        let text = text.boxed();
+        let text = text.visible();
+        Some(
+            if venv.inactive {
+                text.gray()
+            } else {
+                text.yellow()
+            }
+            .with_reset()
+            .invisible()
+            .to_string(),
+        )
     }
 }
 
@@ -57,11 +100,124 @@ impl Icon for Venv {
     }
 }
 
+/// Activated virtualenv, which may be managed by uv, poetry or pipenv
+fn virtual_env() -> MaybeVenv {
+    let path = PathBuf::from(env::var("VIRTUAL_ENV").ok()?);
+    Some(from_venv_dir(&path, false))
+}
+
+fn from_venv_dir(path: &Path, inactive: bool) -> Venv {
+    let in_virtualenvs = path
+        .parent()
+        .is_some_and(|parent| parent.file_name() == Some(OsStr::new("virtualenvs")));
+    let manager = if venv_cfg(path, "uv").unwrap_or_default().is_some() {
+        Manager::Uv
+    } else if in_virtualenvs && path.components().any(|part| part.as_os_str() == "pypoetry") {
+        Manager::Poetry
+    } else if in_virtualenvs {
+        Manager::Pipenv
+    } else {
+        Manager::Venv
+    };
+    let name = venv_name(path);
+    let name = if in_virtualenvs {
+        strip_hash(name)
+    } else {
+        name
+    };
+    // uv writes `version_info` instead of `version`
+    let version = venv_cfg(path, "version")
+        .ok()
+        .flatten()
+        .or_else(|| venv_cfg(path, "version_info").ok().flatten())
+        .unwrap_or("<sys?>".to_owned());
+    Venv {
+        name: name.to_owned(),
+        version,
+        manager,
+        inactive,
+    }
+}
+
+/// Activated conda environment, with python version from its package metadata
+fn conda() -> MaybeVenv {
+    let prefix = PathBuf::from(env::var("CONDA_PREFIX").ok()?);
+    let name = env::var("CONDA_DEFAULT_ENV")
+        .ok()
+        .filter(|name| !name.is_empty())
+        .or_else(|| Some(prefix.file_name()?.to_str()?.to_owned()))
+        .unwrap_or("<conda>".to_owned());
+    let version = fs::read_dir(prefix.join("conda-meta"))
+        .ok()
+        .and_then(|entries| {
+            entries
+                .map_while(Result::ok)
+                .find_map(|entry| Some(conda_python(entry.file_name().to_str()?)?.to_owned()))
+        })
+        .unwrap_or("<sys?>".to_owned());
+    Some(Venv {
+        name,
+        version,
+        manager: Manager::Conda,
+        inactive: false,
+    })
+}
+
+/// Python version from conda package metadata name, like `python-3.12.4-h5148396_1.json`
+fn conda_python(filename: &str) -> Option<&str> {
+    let (version, _build) = filename
+        .strip_suffix(".json")?
+        .strip_prefix("python-")?
+        .split_once('-')?;
+    version
+        .starts_with(|c: char| c.is_ascii_digit())
+        .then_some(version)
+}
+
+/// Project environment which exists but is not activated: `UV_PROJECT_ENVIRONMENT` relative to
+/// the project root, or `.venv` next to the nearest `pyproject.toml`
+fn project_venv(workdir: &Path) -> MaybeVenv {
+    let pyproject = file::upfind(workdir, "pyproject.toml").ok()?;
+    let root = pyproject.parent()?;
+    let path = env::var("UV_PROJECT_ENVIRONMENT")
+        .map_or_else(|_| root.join(".venv"), |dir| root.join(dir));
+    file::points_to_file(&path.join("pyvenv.cfg")).then(|| from_venv_dir(&path, true))
+}
+
+/// Python selected by pyenv shims with `PYENV_VERSION`, as after `pyenv shell` or `pyenv activate`.
+/// The nearest `.python-version` is left to the toolchain block
+fn pyenv() -> MaybeVenv {
+    let root = env::var("PYENV_ROOT").map_or_else(
+        |_| PathBuf::from(env::var("HOME").unwrap_or_default()).join(".pyenv"),
+        PathBuf::from,
+    );
+    let name = env::var("PYENV_VERSION").ok()?;
+    let name = name.split(':').next()?;
+    if name.is_empty() || name == "system" {
+        return None;
+    }
+    // Versions are directories, virtualenvs made by pyenv-virtualenv are symlinks to them
+    let path = root.join("versions").join(name);
+    if !path.is_dir() {
+        return None;
+    }
+    let version = venv_cfg(&path, "version")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| name.to_owned());
+    Some(Venv {
+        name: name.to_owned(),
+        version,
+        manager: Manager::Pyenv,
+        inactive: false,
+    })
+}
+
 fn venv_name(path: &Path) -> &str {
     path.ancestors()
         .filter_map(Path::file_name)
         .filter_map(OsStr::to_str)
-        .find(|name| !["venv", "env", "virtualenv"].contains(name))
+        .find(|name| ![".venv", "venv", "env", "virtualenv"].contains(name))
         .map_or("<venv>", |name| {
             ["venv", "virtualenv", "env", "-", "_"]
                 .iter()
@@ -69,13 +225,31 @@ fn venv_name(path: &Path) -> &str {
         })
 }
 
-fn venv_ver(path: &Path) -> Result<Option<String>> {
+/// Strips the hash which poetry (`name-XXXXXXXX-py3.12`) and pipenv (`name-XXXXXXXX`) add to
+/// environment names
+fn strip_hash(name: &str) -> &str {
+    let name = name
+        .rsplit_once("-py")
+        .filter(|(_, version)| version.starts_with(|c: char| c.is_ascii_digit()))
+        .map_or(name, |(name, _)| name);
+    match name
+        .len()
+        .checked_sub(9)
+        .and_then(|end| name.split_at_checked(end))
+    {
+        Some((stem, hash)) if hash.starts_with('-') && !stem.is_empty() => stem,
+        _ => name,
+    }
+}
+
+/// Reads `key = value` from `pyvenv.cfg`
+fn venv_cfg(path: &Path, key: &str) -> Result<Option<String>> {
     Ok(BufReader::new(File::open(path.join("pyvenv.cfg"))?)
         .lines()
         .find_map(|line| {
             Some(
                 line.ok()?
-                    .strip_prefix("version")?
+                    .strip_prefix(key)?
                     .trim_start_matches(' ')
                     .strip_prefix('=')?
                     .trim_start_matches(' ')
@@ -83,3 +257,42 @@ fn venv_ver(path: &Path) -> Result<Option<String>> {
             )
         }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{conda_python, strip_hash, venv_name};
+    use std::path::Path;
+
+    #[test]
+    fn names() {
+        for (path, expected) in [
+            ("/home/u/proj/.venv", "proj"),
+            ("/home/u/proj/venv", "proj"),
+            ("/home/u/envs/ml-venv", "ml"),
+            ("/", "<venv>"),
+        ] {
+            assert_eq!(venv_name(Path::new(path)), expected, "{path}");
+        }
+        for (name, expected) in [
+            ("my-proj-Ab_d-1Zx-py3.12", "my-proj"),
+            ("proj-AbCd1234", "proj"),
+            ("short", "short"),
+            ("no-hash-here-py", "no-hash-here-py"),
+        ] {
+            assert_eq!(strip_hash(name), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn conda_meta() {
+        for (file, expected) in [
+            ("python-3.12.4-h5148396_1.json", Some("3.12.4")),
+            ("python-dateutil-2.9.0-pyhd8ed1ab_0.json", None),
+            ("python_abi-3.12-4_cp312.json", None),
+            ("openssl-3.3.1-h4ab18f5_0.json", None),
+            ("python-3.11.9-hb806964_0_cpython.json", Some("3.11.9")),
+        ] {
+            assert_eq!(conda_python(file), expected, "{file}");
+        }
+    }
+}
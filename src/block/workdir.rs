@@ -1,4 +1,4 @@
-use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _};
+use crate::{Environment, Extend, Icon, IconMode, Pretty, Style as _, hyperlink::percent_encode};
 use anyhow::{Context as _, Result, ensure};
 use rustix::fs as rfs;
 use std::{
@@ -106,6 +106,7 @@ pub struct Workdir {
     work_dir: PathBuf,
     git_tree: Option<PathBuf>,
     current_home: Option<(PathBuf, String)>,
+    host: String,
     state: State,
 }
 
@@ -125,6 +126,7 @@ impl From<&Environment> for Workdir {
             work_dir,
             git_tree,
             current_home,
+            host: env.host.clone(),
             state,
         }
     }
@@ -180,6 +182,15 @@ impl Pretty for Workdir {
             .join("/")
             + &highlighted_str.unwrap_or_default();
 
-        Some(format!("{}{}", self.state.pretty(mode).unwrap(), work_dir))
+        let url = format!(
+            "file://{}{}",
+            self.host,
+            percent_encode(&self.work_dir.to_string_lossy())
+        );
+        Some(format!(
+            "{}{}",
+            self.state.pretty(mode).unwrap(),
+            work_dir.hyperlink(&url)
+        ))
     }
 }
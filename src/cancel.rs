@@ -0,0 +1,261 @@
+//! Cancellation of the extended redraw
+//!
+//! The shell gives every prompt its own control pipe and writes to it once the prompt is no
+//! longer current: when a command is submitted or another prompt is drawn. Extended render checks
+//! the pipe at safe points --- while waiting for git, and right before painting. The paint is a
+//! single `write`, so a cancelled redraw never leaves half of an escape sequence on the terminal.
+//! The check and the write are not atomic, though: if the shell moves on right between them, the
+//! paint still lands, relative to wherever the cursor is by then. That window is microseconds
+//! long, while the wait for git which the pipe cuts short can take seconds. Closing the pipe, like
+//! the shell does when it exits, cancels too, and so does hangup of the terminal.
+//!
+//! Resize repaints are drawn relative to the cursor, which is on the prompt row only until the
+//! user types something. Readline reads typed input in the shell itself, so [`Typing`] tells it by
+//! the bytes the shell has read since it took the prompt
+
+use rustix::{
+    event::{PollFd, PollFlags, poll},
+    fs::{Mode, OFlags, open},
+    io::{Errno, FdFlags, fcntl_setfd, ioctl_fionread, read},
+};
+use std::{
+    fs,
+    io::{self, Read as _, Write as _},
+    mem::MaybeUninit,
+    os::fd::{AsFd, AsRawFd as _, BorrowedFd, FromRawFd as _, OwnedFd},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    ptr,
+    sync::OnceLock,
+    thread,
+    time::Duration,
+};
+
+static CONTROL: OnceLock<OwnedFd> = OnceLock::new();
+
+/// Watch this control pipe for cancellation. Only the first call has an effect
+pub fn install(fd: OwnedFd) {
+    // The shell passes both ends of the pipe. Keep only the reading one, so the pipe is seen
+    // closed once the shell closes it
+    let fd = open(
+        format!("/proc/self/fd/{}", fd.as_raw_fd()),
+        OFlags::RDONLY | OFlags::NONBLOCK | OFlags::CLOEXEC,
+        Mode::empty(),
+    )
+    .unwrap_or_else(|_| {
+        // Children, like git, must not keep the pipe
+        fcntl_setfd(&fd, FdFlags::CLOEXEC).unwrap_or_default();
+        fd
+    });
+    CONTROL.set(fd).unwrap_or_default();
+}
+
+/// The control pipe being watched, if any
+#[must_use]
+pub fn control() -> Option<BorrowedFd<'static>> {
+    CONTROL.get().map(AsFd::as_fd)
+}
+
+/// Whether anything was written to the control pipe, or it was closed. Never cancelled without
+/// one
+#[must_use]
+pub fn cancelled() -> bool {
+    CONTROL.get().is_some_and(|fd| {
+        poll(&mut [PollFd::new(fd, PollFlags::IN)], 0).is_ok_and(|ready| ready > 0)
+    })
+}
+
+/// Paints to the terminal with one `write`, unless cancelled
+pub fn paint(text: &str) {
+    if !cancelled() {
+        io::stderr()
+            .lock()
+            .write_all(text.as_bytes())
+            .unwrap_or_default();
+    }
+}
+
+/// Waits a little for the reader of the pipe to take everything written to it. False if it has not,
+/// or this is not a pipe
+pub fn drained(pipe: impl AsFd) -> bool {
+    for _ in 0_i32..100_i32 {
+        match ioctl_fionread(&pipe) {
+            Ok(0) => return true,
+            Ok(_) => thread::sleep(Duration::from_millis(1)),
+            Err(_) => return false,
+        }
+    }
+    false
+}
+
+/// Bytes read by the shell since watching started
+pub struct Typing {
+    io: PathBuf,
+    read: u64,
+}
+
+impl Typing {
+    /// Starts watching reads of this shell. It must have read the prompt already
+    #[must_use]
+    pub fn watch(shell: i32) -> Option<Typing> {
+        let io = PathBuf::from(format!("/proc/{shell}/io"));
+        let read = read_chars(&io)?;
+        Some(Typing { io, read })
+    }
+
+    /// Whether the shell has read anything since, or it can not be told anymore
+    #[must_use]
+    pub fn started(&self) -> bool {
+        read_chars(&self.io) != Some(self.read)
+    }
+}
+
+/// `rchar` of `/proc/<pid>/io`, which counts bytes read from any file, terminal included
+fn read_chars(io: &Path) -> Option<u64> {
+    fs::read_to_string(io)
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("rchar:"))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Calls `repaint` right away and whenever the terminal is resized, until cancelled or the
+/// terminal is hung up. Returns at once without a control pipe, since nothing would stop it then
+pub fn on_resize(mut repaint: impl FnMut()) {
+    let Some(control) = CONTROL.get() else {
+        return;
+    };
+    let Some(resized) = resize_signal() else {
+        return;
+    };
+    // Resize could have happened before the signal was blocked
+    repaint();
+    // Standard input is `/dev/null` in the background, the terminal is on standard error
+    let tty = io::stderr();
+    let mut info = [0_u8; size_of::<libc::signalfd_siginfo>()];
+
+    loop {
+        let (cancel, resize) = {
+            let mut fds = [
+                PollFd::new(control, PollFlags::IN),
+                PollFd::new(&resized, PollFlags::IN),
+                // Only hangup is reported without events
+                PollFd::new(&tty, PollFlags::empty()),
+            ];
+            match poll(&mut fds, -1) {
+                Ok(_) => {}
+                Err(Errno::INTR) => continue,
+                Err(_) => return,
+            }
+            let [written, signaled, terminal] = fds.map(|fd| fd.revents());
+            (
+                !written.is_empty() || terminal.contains(PollFlags::HUP),
+                !signaled.is_empty(),
+            )
+        };
+        if cancel {
+            return;
+        }
+        if resize {
+            read(&resized, &mut info).unwrap_or_default();
+            if !cancelled() {
+                repaint();
+            }
+        }
+    }
+}
+
+/// Blocks `SIGWINCH`, so it is only delivered through the returned signalfd
+fn resize_signal() -> Option<OwnedFd> {
+    let mut set = MaybeUninit::<libc::sigset_t>::uninit();
+    // SAFETY: `set` is a valid pointer, and it is initialized here
+    if unsafe { libc::sigemptyset(set.as_mut_ptr()) } != 0_i32 {
+        return None;
+    }
+    // SAFETY: `set` is a valid pointer to the set initialized above
+    if unsafe { libc::sigaddset(set.as_mut_ptr(), libc::SIGWINCH) } != 0_i32 {
+        return None;
+    }
+    // SAFETY: `set` was initialized by `sigemptyset` above
+    let set = unsafe { set.assume_init() };
+    // SAFETY: `set` is initialized, and the old mask is not asked for
+    if unsafe { libc::sigprocmask(libc::SIG_BLOCK, &raw const set, ptr::null_mut()) } != 0_i32 {
+        return None;
+    }
+    // SAFETY: `set` is initialized, and -1 asks for a new signalfd
+    let fd = unsafe { libc::signalfd(-1, &raw const set, libc::SFD_CLOEXEC) };
+    // SAFETY: signalfd returned a new file descriptor which is owned by nothing else
+    (fd >= 0_i32).then(|| unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Runs the command and collects its standard output. The child is killed and `None` is returned
+/// if cancelled before it finishes
+pub(crate) fn stdout(command: &mut Command) -> Option<Vec<u8>> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut pipe = child.stdout.take()?;
+    let mut out = vec![];
+    let mut buf = [0_u8; 4096];
+
+    let finished = loop {
+        let (readable, cancel) = {
+            let mut fds = vec![PollFd::new(&pipe, PollFlags::IN)];
+            if let Some(control) = CONTROL.get() {
+                fds.push(PollFd::new(control, PollFlags::IN));
+            }
+            match poll(&mut fds, -1) {
+                Ok(_) => {}
+                Err(Errno::INTR) => continue,
+                Err(_) => break false,
+            }
+            let ready = |fd: &PollFd| !fd.revents().is_empty();
+            (
+                fds.first().is_some_and(ready),
+                fds.get(1).is_some_and(ready),
+            )
+        };
+        if cancel {
+            break false;
+        }
+        if readable {
+            match pipe.read(&mut buf) {
+                Ok(0) => break true,
+                Ok(len) => out.extend_from_slice(&buf[..len]),
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => break false,
+            }
+        }
+    };
+
+    if !finished {
+        child.kill().unwrap_or_default();
+    }
+    child.wait().ok()?;
+    finished.then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stdout;
+    use std::process::Command;
+
+    #[test]
+    fn uncancelled() {
+        assert_eq!(
+            stdout(Command::new("printf").arg("a\\nb")).as_deref(),
+            Some(&b"a\nb"[..]),
+            "whole output without control pipe"
+        );
+        assert_eq!(
+            stdout(&mut Command::new("/nonexistent")),
+            None,
+            "not started"
+        );
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{Icon, IconMode, virt};
+use crate::{
+    Icon, IconMode, Probe,
+    virt::{self, ContainerType, VirtualizationType},
+};
 use std::{
     fs::{File, read_to_string},
     io::{BufRead as _, BufReader},
@@ -6,7 +9,7 @@ use std::{
 };
 
 /// Chassis type, according to hostnamectl
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum Chassis {
     /// Desktops, nettops, etc
@@ -25,10 +28,10 @@ pub enum Chassis {
     Watch,
     /// Embedded devices
     Embedded,
-    /// Virtual machines
-    Virtual,
-    /// Containered environments
-    Container,
+    /// Virtual machines, with hypervisor type
+    Virtual(VirtualizationType),
+    /// Containered environments, with container manager type
+    Container(ContainerType),
     /// Something else
     Unknown,
 }
@@ -44,8 +47,8 @@ impl From<&str> for Chassis {
             "handset" => Chassis::Handset,
             "watch" => Chassis::Watch,
             "embedded" => Chassis::Embedded,
-            "vm" => Chassis::Virtual,
-            "container" => Chassis::Container,
+            "vm" => Chassis::Virtual(VirtualizationType::Other),
+            "container" => Chassis::Container(ContainerType::Other),
             _ => Chassis::Unknown,
         }
     }
@@ -87,14 +90,8 @@ impl Icon for Chassis {
                 Text => "Emb",
                 Icons | MinimalIcons => " ",
             },
-            Self::Virtual => match mode {
-                Text => "Virt",
-                Icons | MinimalIcons => " ",
-            },
-            Self::Container => match mode {
-                Text => "Cont",
-                Icons | MinimalIcons => " ",
-            },
+            Self::Virtual(vm) => vm.icon(mode),
+            Self::Container(container) => container.icon(mode),
             Self::Unknown => match mode {
                 Text => "Unkn",
                 Icons | MinimalIcons => "??", // TODO: find "unknown" icon
@@ -108,18 +105,59 @@ impl Chassis {
     ///
     /// Containered and virtual environments are likely to be misdetected. You can try overriding
     /// this via `/etc/machine-info` or `hostnamectl set-chassis`...
+    #[must_use]
     pub fn get() -> Chassis {
-        None.or_else(Chassis::try_machine_info)
-            .or_else(Chassis::try_container)
-            .or_else(Chassis::try_udev)
-            .or_else(Chassis::try_virtualization)
-            .or_else(Chassis::try_dmi_type)
-            .or_else(Chassis::try_acpi_profile)
-            .or_else(Chassis::try_devtree_type)
-            .unwrap_or(Chassis::Unknown)
+        Chassis::detect(&Probe::default())
+    }
+
+    /// Gets chassis type from files under probe's root, noting the reasons
+    #[must_use]
+    pub fn detect(probe: &Probe) -> Chassis {
+        match None
+            .or_else(|| Chassis::try_machine_info(probe))
+            .or_else(|| Chassis::try_container(probe))
+            .or_else(|| Chassis::try_udev(probe))
+            .or_else(|| Chassis::try_virtualization(probe))
+            .or_else(|| Chassis::try_dmi_type(probe))
+            .or_else(|| Chassis::try_acpi_profile(probe))
+            .or_else(|| Chassis::try_devtree_type(probe))
+        {
+            // Chassis overrides only tell "vm" or "container", so ask the detector which one
+            Some(Chassis::Virtual(VirtualizationType::Other)) => Chassis::try_virtualization(probe)
+                .unwrap_or(Chassis::Virtual(VirtualizationType::Other)),
+            Some(Chassis::Container(ContainerType::Other)) => {
+                Chassis::try_container(probe).unwrap_or(Chassis::Container(ContainerType::Other))
+            }
+            Some(chassis) => chassis,
+            None => {
+                probe.note("no chassis information found");
+                Chassis::Unknown
+            }
+        }
     }
 
-    fn try_machine_info() -> Option<Chassis> {
+    /// Hypervisor or container manager name, like `kvm` or `docker`, if known
+    #[must_use]
+    pub fn detail(&self) -> Option<&'static str> {
+        match self {
+            Chassis::Virtual(VirtualizationType::Other)
+            | Chassis::Container(ContainerType::Other) => None,
+            Chassis::Virtual(vm) => Some(vm.name()),
+            Chassis::Container(container) => Some(container.name()),
+            _ => None,
+        }
+    }
+
+    /// Whether the device is likely to be running on battery
+    #[must_use]
+    pub fn is_portable(&self) -> bool {
+        matches!(
+            self,
+            Chassis::Laptop | Chassis::Convertible | Chassis::Tablet | Chassis::Handset
+        )
+    }
+
+    fn try_machine_info(probe: &Probe) -> Option<Chassis> {
         /*
         /etc/machine-info into lines
         find-map
@@ -131,21 +169,12 @@ impl Chassis {
         | unquote
         | chassis_str
         */
-        BufReader::new(File::open("/etc/machine-info").ok()?)
-            .lines()
-            .find_map(|x| {
-                Some(Chassis::from(
-                    x.ok()?
-                        .trim()
-                        .strip_prefix("CHASSIS")?
-                        .trim_start()
-                        .strip_prefix('=')?
-                        .trim_start(),
-                ))
-            })
+        let chassis = machine_info(probe, "CHASSIS")?;
+        probe.note(format!("machine-info: CHASSIS={chassis}"));
+        Some(Chassis::from(chassis.as_str()))
     }
 
-    fn try_udev() -> Option<Chassis> {
+    fn try_udev(probe: &Probe) -> Option<Chassis> {
         /*
         sd-device /sys/class/dmi/id points to /run/udev/data/+dmi:id
         hours wasted on this: about three,
@@ -156,73 +185,225 @@ impl Chassis {
         I can't be 100% sure this code works as NO machines I have acceess to
           have any chassis-related information in this file
         */
-        BufReader::new(File::open("/run/udev/data/+dmi:id").ok()?)
+        let chassis = BufReader::new(File::open(probe.path("/run/udev/data/+dmi:id")).ok()?)
             .lines()
-            .find_map(|x| Some(Chassis::from(x.ok()?.strip_prefix("E:ID_CHASSIS=")?.trim())))
+            .find_map(|x| Some(x.ok()?.strip_prefix("E:ID_CHASSIS=")?.trim().to_owned()))?;
+        probe.note(format!("udev: ID_CHASSIS={chassis}"));
+        Some(Chassis::from(chassis.as_str()))
     }
 
-    fn try_virtualization() -> Option<Chassis> {
+    fn try_virtualization(probe: &Probe) -> Option<Chassis> {
         // No one knows if this works correctly
-        virt::detect_vm().ok()?.map(|_| Chassis::Virtual)
+        virt::detect_vm(probe).ok()?.map(Chassis::Virtual)
     }
 
-    fn try_container() -> Option<Chassis> {
+    fn try_container(probe: &Probe) -> Option<Chassis> {
         // No one knows if this works correctly
-        virt::detect_container().ok()?.map(|_| Chassis::Container)
+        virt::detect_container(probe).ok()?.map(Chassis::Container)
     }
 
-    fn try_dmi_type() -> Option<Chassis> {
+    fn try_dmi_type(probe: &Probe) -> Option<Chassis> {
         /*
-        /sys/class/dmi/id/chassis_type as u32 in hex
+        /sys/class/dmi/id/chassis_type as u32, values are from SMBIOS spec, which uses hex
         3, 4, 6, 7, D, 23, 24 -> desktop
         8, 9, A, E -> laptop
         B -> handset
-        11, 1C, 1D -> server
+        11, 17, 1C, 1D -> server
         1E -> tablet
         1F, 20 -> convertible
         21, 22 -> embedded
+        others are not known and detection continues
         */
-        Some(match read_single_u32("/sys/class/dmi/id/chassis_type")? {
+        let chassis_type = read_single_u32(&probe.path("/sys/class/dmi/id/chassis_type"))?;
+        let chassis = match chassis_type {
             0x03 | 0x04 | 0x06 | 0x07 | 0x0d | 0x23 | 0x24 => Chassis::Desktop,
             0x08 | 0x09 | 0x0a | 0x0e => Chassis::Laptop,
             0x0b => Chassis::Handset,
-            0x11 | 0x1c | 0x1d => Chassis::Server,
+            0x11 | 0x17 | 0x1c | 0x1d => Chassis::Server,
             0x1e => Chassis::Tablet,
             0x1f | 0x20 => Chassis::Convertible,
             0x21 | 0x22 => Chassis::Embedded,
-            _ => Chassis::Unknown,
-        })
+            _ => None?,
+        };
+        probe.note(format!("dmi: chassis type is {chassis_type:#04x}"));
+        Some(chassis)
     }
 
-    fn try_acpi_profile() -> Option<Chassis> {
+    fn try_acpi_profile(probe: &Probe) -> Option<Chassis> {
         /*
         /sys/firmware/acpi/pm_profile as u32 in dec
         1, 3, 6 -> desktop
         2 -> laptop
         4, 5, 7 -> server
         8 -> tablet
+        others are not known and detection continues
         */
-        Some(match read_single_u32("/sys/firmware/acpi/pm_profile")? {
+        let profile = read_single_u32(&probe.path("/sys/firmware/acpi/pm_profile"))?;
+        let chassis = match profile {
             1 | 3 | 6 => Chassis::Desktop,
             2 => Chassis::Laptop,
             4 | 5 | 7 => Chassis::Server,
             8 => Chassis::Tablet,
-            _ => Chassis::Unknown,
-        })
+            _ => None?,
+        };
+        probe.note(format!(
+            "acpi: preferred power management profile is {profile}"
+        ));
+        Some(chassis)
     }
 
-    fn try_devtree_type() -> Option<Chassis> {
+    fn try_devtree_type(probe: &Probe) -> Option<Chassis> {
         /*
-        /proc/device-tree/chassis-type as chassis_str
+        /proc/device-tree/chassis-type as chassis_str, NUL-terminated
         */
-        Some(Chassis::from(
-            read_to_string("/proc/device-tree/chassis-type")
-                .ok()?
-                .as_str(),
-        ))
+        let chassis = read_to_string(probe.path("/proc/device-tree/chassis-type")).ok()?;
+        let chassis = chassis.trim_end_matches('\0');
+        probe.note(format!("device tree: chassis type is {chassis:?}"));
+        Some(Chassis::from(chassis))
     }
 }
 
+/// Reads an unquoted value from `/etc/machine-info`, which is a shell-like `KEY=value` file
+pub(crate) fn machine_info(probe: &Probe, key: &str) -> Option<String> {
+    BufReader::new(File::open(probe.path("/etc/machine-info")).ok()?)
+        .lines()
+        .find_map(|x| {
+            Some(
+                x.ok()?
+                    .trim()
+                    .strip_prefix(key)?
+                    .trim_start()
+                    .strip_prefix('=')?
+                    .trim_start()
+                    .trim_matches(['"', '\''])
+                    .to_owned(),
+            )
+        })
+}
+
 fn read_single_u32<T: AsRef<Path> + ?Sized>(path: &T) -> Option<u32> {
     read_to_string(path).ok()?.trim().parse::<u32>().ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Chassis;
+    use crate::{ContainerType, VirtualizationType, probe::fixture::Fixture};
+
+    /// Case name, files under the fake root, and expected chassis
+    type Case<'a> = (&'a str, &'a [(&'a str, &'a str)], Chassis);
+
+    #[test]
+    fn chassis_matrix() {
+        let cases: &[Case] = &[
+            ("nothing", &[], Chassis::Unknown),
+            (
+                "machine-info",
+                &[(
+                    "/etc/machine-info",
+                    "PRETTY_HOSTNAME=\"Reimu\"\nCHASSIS=\"laptop\"\n",
+                )],
+                Chassis::Laptop,
+            ),
+            (
+                "machine-info overrides dmi",
+                &[
+                    ("/etc/machine-info", "CHASSIS=server\n"),
+                    ("/sys/class/dmi/id/chassis_type", "3\n"),
+                ],
+                Chassis::Server,
+            ),
+            (
+                "machine-info vm is refined",
+                &[
+                    ("/etc/machine-info", "CHASSIS=vm\n"),
+                    ("/sys/class/dmi/id/sys_vendor", "VMware, Inc.\n"),
+                ],
+                Chassis::Virtual(VirtualizationType::VMware),
+            ),
+            (
+                "machine-info vm is kept",
+                &[("/etc/machine-info", "CHASSIS=vm\n")],
+                Chassis::Virtual(VirtualizationType::Other),
+            ),
+            (
+                "container before dmi",
+                &[
+                    ("/.dockerenv", ""),
+                    ("/sys/class/dmi/id/chassis_type", "3\n"),
+                ],
+                Chassis::Container(ContainerType::Docker),
+            ),
+            (
+                "udev",
+                &[("/run/udev/data/+dmi:id", "S:foo\nE:ID_CHASSIS=tablet\n")],
+                Chassis::Tablet,
+            ),
+            (
+                "vm before dmi",
+                &[
+                    ("/sys/class/dmi/id/product_name", "KVM\n"),
+                    ("/sys/class/dmi/id/chassis_type", "1\n"),
+                ],
+                Chassis::Virtual(VirtualizationType::Kvm),
+            ),
+            (
+                "dmi notebook",
+                &[("/sys/class/dmi/id/chassis_type", "10\n")],
+                Chassis::Laptop,
+            ),
+            (
+                "dmi convertible",
+                &[("/sys/class/dmi/id/chassis_type", "31\n")],
+                Chassis::Convertible,
+            ),
+            (
+                "dmi rack mount",
+                &[("/sys/class/dmi/id/chassis_type", "23\n")],
+                Chassis::Server,
+            ),
+            (
+                "dmi other falls back to acpi",
+                &[
+                    ("/sys/class/dmi/id/chassis_type", "1\n"),
+                    ("/sys/firmware/acpi/pm_profile", "4\n"),
+                ],
+                Chassis::Server,
+            ),
+            (
+                "acpi mobile",
+                &[("/sys/firmware/acpi/pm_profile", "2\n")],
+                Chassis::Laptop,
+            ),
+            (
+                "device tree",
+                &[("/proc/device-tree/chassis-type", "handset\0")],
+                Chassis::Handset,
+            ),
+        ];
+
+        for (name, files, expected) in cases {
+            assert_eq!(
+                Chassis::detect(&Fixture::new(files).probe()),
+                *expected,
+                "case {name}"
+            );
+        }
+    }
+
+    #[test]
+    fn reasons_are_noted() {
+        let fixture = Fixture::new(&[("/sys/class/dmi/id/chassis_type", "10\n")]);
+        let probe = fixture.probe();
+        assert_eq!(
+            Chassis::detect(&probe),
+            Chassis::Laptop,
+            "notebook is a laptop"
+        );
+        assert_eq!(
+            probe.notes(),
+            ["dmi: chassis type is 0x0a"],
+            "only the deciding source should be noted"
+        );
+    }
+}
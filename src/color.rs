@@ -0,0 +1,538 @@
+use crate::glob;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+static INSTALLED: OnceLock<ColorDepth> = OnceLock::new();
+static INSTALLED_RULES: OnceLock<ColorRules> = OnceLock::new();
+
+const HSV_COLOR_TABLE: [(u8, u8, u8); 24] = [
+    (255, 0, 0),
+    (255, 85, 0),
+    (255, 128, 0),
+    (255, 170, 0),
+    (255, 213, 0),
+    (255, 255, 0),
+    (213, 255, 0),
+    (170, 255, 0),
+    (128, 255, 0),
+    (0, 255, 85),
+    (0, 255, 128),
+    (0, 255, 170),
+    (0, 255, 213),
+    (0, 213, 255),
+    (0, 128, 255),
+    (0, 85, 255),
+    (128, 0, 255),
+    (170, 0, 255),
+    (213, 0, 255),
+    (255, 0, 255),
+    (255, 0, 212),
+    (255, 0, 170),
+    (255, 0, 128),
+    (255, 0, 85),
+];
+
+/// Default xterm colors for the 16-color palette, as CSI 30..37 and CSI 90..97 show them
+const ANSI_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Channel values of the 6x6x6 color cube in the 256-color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// How many colors the terminal can show
+///
+/// True colors are downsampled to the nearest color the terminal has, and no colors are shown at
+/// all in monochrome mode. Styles are formatted for [`ColorDepth::TrueColor`] until some other
+/// depth is [installed](ColorDepth::install)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ColorDepth {
+    /// 24-bit colors
+    TrueColor,
+    /// xterm 256-color palette
+    Palette256,
+    /// 16 basic ANSI colors
+    Palette16,
+    /// No colors
+    Monochrome,
+}
+
+impl ColorDepth {
+    /// Detect color depth of the terminal
+    ///
+    /// | Environment                         | Resulting depth           |
+    /// |-------------------------------------|---------------------------|
+    /// | `NO_COLOR` is not empty             | Monochrome                |
+    /// | `PS1_COLORS=truecolor\|256\|16\|none` | As requested              |
+    /// | `COLORTERM=truecolor\|24bit`         | True color                |
+    /// | `TERM=dumb`                         | Monochrome                |
+    /// | `TERM=linux`                        | 16 colors                 |
+    /// | `TERM=*-256color`                   | 256 colors                |
+    /// | `TERM=*-direct`                     | True color                |
+    /// | other `TERM` with terminfo entry    | As `colors` capability    |
+    /// | otherwise                           | True color                |
+    #[must_use]
+    pub fn build() -> Self {
+        if env::var("NO_COLOR").is_ok_and(|x| !x.is_empty()) {
+            return Self::Monochrome;
+        }
+        match env::var("PS1_COLORS").as_deref() {
+            Ok("truecolor" | "24bit") => return Self::TrueColor,
+            Ok("256") => return Self::Palette256,
+            Ok("16") => return Self::Palette16,
+            Ok("none") => return Self::Monochrome,
+            _ => {}
+        }
+        if let Ok("truecolor" | "24bit") = env::var("COLORTERM").as_deref() {
+            return Self::TrueColor;
+        }
+        let Ok(term) = env::var("TERM") else {
+            return Self::TrueColor;
+        };
+        match term.as_str() {
+            "dumb" => Self::Monochrome,
+            "linux" => Self::Palette16,
+            _ if term.ends_with("-256color") => Self::Palette256,
+            _ if term.ends_with("-direct") => Self::TrueColor,
+            _ => terminfo_colors(&term).map_or(Self::TrueColor, Self::from_colors),
+        }
+    }
+
+    /// Depth from terminfo's `colors` capability
+    fn from_colors(colors: u32) -> Self {
+        match colors {
+            0x0100_0000.. => Self::TrueColor,
+            256.. => Self::Palette256,
+            8.. => Self::Palette16,
+            _ => Self::Monochrome,
+        }
+    }
+
+    /// Use this depth for all styles. Only the first call has an effect
+    pub fn install(self) {
+        INSTALLED.set(self).unwrap_or_default();
+    }
+
+    /// Installed color depth, true color by default
+    #[must_use]
+    pub fn current() -> Self {
+        INSTALLED.get().copied().unwrap_or(Self::TrueColor)
+    }
+
+    /// SGR parameters for foreground color, nearest to given true color. Empty if monochrome
+    ///
+    /// ```
+    /// use statusline::ColorDepth;
+    ///
+    /// assert_eq!("38;2;255;100;203", ColorDepth::TrueColor.foreground(255, 100, 203));
+    /// assert_eq!("38;5;206", ColorDepth::Palette256.foreground(255, 100, 203));
+    /// assert_eq!("95", ColorDepth::Palette16.foreground(255, 100, 203));
+    /// assert_eq!("", ColorDepth::Monochrome.foreground(255, 100, 203));
+    /// ```
+    #[must_use]
+    pub fn foreground(self, red: u8, green: u8, blue: u8) -> String {
+        match self {
+            Self::TrueColor => format!("38;2;{red};{green};{blue}"),
+            Self::Palette256 => format!("38;5;{}", nearest_256((red, green, blue))),
+            Self::Palette16 => {
+                let index = nearest_16((red, green, blue));
+                if index < 8 {
+                    format!("{}", index + 30)
+                } else {
+                    format!("{}", index + 82)
+                }
+            }
+            Self::Monochrome => String::new(),
+        }
+    }
+}
+
+/// Squared "redmean" color distance, which is closer to perceived difference than plain RGB one
+fn distance(left: (u8, u8, u8), right: (u8, u8, u8)) -> u32 {
+    let diff = |a: u8, b: u8| u32::from(a.abs_diff(b)).pow(2);
+    let red_mean = u32::midpoint(u32::from(left.0), u32::from(right.0));
+    (512 + red_mean) * diff(left.0, right.0)
+        + 1024 * diff(left.1, right.1)
+        + (767 - red_mean) * diff(left.2, right.2)
+}
+
+fn nearest_16(color: (u8, u8, u8)) -> usize {
+    (0..ANSI_PALETTE.len())
+        .min_by_key(|&index| distance(color, ANSI_PALETTE[index]))
+        .unwrap_or_default()
+}
+
+fn nearest_256(color: (u8, u8, u8)) -> usize {
+    let level = |channel: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&index| CUBE_LEVELS[index].abs_diff(channel))
+            .unwrap_or_default()
+    };
+    let (r, g, b) = (level(color.0), level(color.1), level(color.2));
+    let cube = (CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]);
+
+    // Grayscale ramp is 8, 18, ..., 238
+    let average = (u32::from(color.0) + u32::from(color.1) + u32::from(color.2)) / 3;
+    let step = usize::try_from(average.saturating_sub(3) / 10)
+        .unwrap_or_default()
+        .min(23);
+    let gray = u8::try_from(8 + step * 10).unwrap_or(u8::MAX);
+    let gray = (gray, gray, gray);
+
+    if distance(color, gray) < distance(color, cube) {
+        232 + step
+    } else {
+        16 + 36 * r + 6 * g + b
+    }
+}
+
+/// Color of a pinned name
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Color {
+    /// Dark color from 16-color palette, as in [`crate::Style::low_color`]
+    Low(usize),
+    /// True color
+    True(u8, u8, u8),
+}
+
+impl Color {
+    /// Parses a color name, like `red` or `light_green`, or `#rrggbb`
+    ///
+    /// ```
+    /// use statusline::Color;
+    ///
+    /// assert_eq!(Some(Color::Low(0)), Color::parse("red"));
+    /// assert_eq!(Some(Color::True(255, 128, 0)), Color::parse("orange"));
+    /// assert_eq!(Some(Color::True(18, 52, 86)), Color::parse("#123456"));
+    /// assert_eq!(None, Color::parse("ultraviolet"));
+    /// ```
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "red" => Self::Low(0),
+            "green" => Self::Low(1),
+            "yellow" => Self::Low(2),
+            "blue" => Self::Low(3),
+            "purple" => Self::Low(4),
+            "cyan" => Self::Low(5),
+            "light_gray" => Self::Low(6),
+            "pink" => Self::True(255, 100, 203),
+            "light_green" => Self::True(100, 255, 100),
+            "light_red" => Self::True(255, 80, 100),
+            "gray" => Self::True(128, 128, 128),
+            "orange" => Self::True(255, 128, 0),
+            _ => {
+                let hex = name.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+                let channel = |at: usize| u8::from_str_radix(hex.get(at..at + 2)?, 16).ok();
+                Self::True(channel(0)?, channel(2)?, channel(4)?)
+            }
+        })
+    }
+}
+
+/// Pinned colors and hash seed for [`crate::Style::colorize_with`]
+///
+/// Rules are `pattern=color` pairs, where pattern may contain `*` and `?`. The first matching rule
+/// wins. Names without a matching rule get one of 23 colors by hash, which depends on the seed.
+/// `root` is red unless some rule says otherwise. Nothing is pinned until rules are
+/// [installed](ColorRules::install)
+///
+/// ```
+/// use statusline::{Color, ColorRules};
+///
+/// let rules = ColorRules::parse("prod-db-01=red *.prod.example.com=#ff0000 staging-*=orange", 0);
+/// assert_eq!(Some(Color::Low(0)), rules.pinned("prod-db-01"));
+/// assert_eq!(Some(Color::True(255, 128, 0)), rules.pinned("staging-web"));
+/// assert_eq!(None, rules.pinned("dev-box"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ColorRules {
+    rules: Vec<(String, Color)>,
+    seed: usize,
+}
+
+impl ColorRules {
+    /// Reads rules from `PS1_COLOR_RULES` and seed from `PS1_COLOR_SEED`
+    #[must_use]
+    pub fn build() -> Self {
+        Self::parse(
+            &env::var("PS1_COLOR_RULES").unwrap_or_default(),
+            env::var("PS1_COLOR_SEED")
+                .ok()
+                .and_then(|seed| seed.parse().ok())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Parses whitespace-separated `pattern=color` rules. Malformed rules are skipped
+    #[must_use]
+    pub fn parse(rules: &str, seed: usize) -> Self {
+        ColorRules {
+            rules: rules
+                .split_whitespace()
+                .filter_map(|rule| {
+                    let (pattern, color) = rule.rsplit_once('=')?;
+                    Some((pattern.to_owned(), Color::parse(color)?))
+                })
+                .collect(),
+            seed,
+        }
+    }
+
+    /// Replaces hash seed, to try resolving collisions
+    #[must_use]
+    pub fn with_seed(self, seed: usize) -> Self {
+        ColorRules { seed, ..self }
+    }
+
+    /// Use these rules for all colorized names. Only the first call has an effect
+    pub fn install(self) {
+        INSTALLED_RULES.set(self).unwrap_or_default();
+    }
+
+    /// Installed rules, or no rules with zero seed by default
+    #[must_use]
+    pub fn current() -> &'static Self {
+        INSTALLED_RULES.get_or_init(Self::default)
+    }
+
+    /// Rules in order, as patterns and colors
+    pub fn rules(&self) -> impl Iterator<Item = (&str, Color)> {
+        self.rules
+            .iter()
+            .map(|(pattern, color)| (pattern.as_str(), *color))
+    }
+
+    /// Hash seed
+    #[must_use]
+    pub fn seed(&self) -> usize {
+        self.seed
+    }
+
+    /// Color of the first rule matching the name, if any
+    #[must_use]
+    pub fn pinned(&self, name: &str) -> Option<Color> {
+        self.rules()
+            .find(|(pattern, _)| glob::matches(pattern, name))
+            .map(|(_, color)| color)
+    }
+
+    /// Color for the name: pinned one, red for `root`, or hashed one otherwise
+    #[must_use]
+    pub fn color_for(&self, name: &str) -> Color {
+        if let Some(color) = self.pinned(name) {
+            return color;
+        }
+        if name == "root" {
+            return Color::Low(0);
+        }
+        let multiplier = hash_multiplier(self.seed);
+        let (r, g, b) = HSV_COLOR_TABLE[polyhash(name, 23, multiplier, name.len()) + 1];
+        Color::True(r, g, b)
+    }
+}
+
+/// Multiplier is changed by the seed, as changing the initial value does not resolve collisions of
+/// names with equal lengths. Seeds from 0 to 21 are mapped onto the 22 multipliers which are not
+/// divisible by 23, starting from 179 for the default seed, and larger seeds repeat them
+fn hash_multiplier(seed: usize) -> usize {
+    let n = seed % 22;
+    // 179 + 5 is the first multiple of 23 to skip
+    179 + n + (n + 18) / 23
+}
+
+fn polyhash(s: &str, m: usize, p: usize, h_init: usize) -> usize {
+    let mut h = h_init % m;
+    for by in s.bytes() {
+        h = (h * p + by as usize) % m;
+    }
+    h
+}
+
+/// Looks up the compiled terminfo entry for the terminal, the same places ncurses does
+fn terminfo_colors(term: &str) -> Option<u32> {
+    let first = term.chars().next()?;
+    let home = env::var("HOME")
+        .ok()
+        .map(|home| Path::new(&home).join(".terminfo"));
+    let dirs = env::var("TERMINFO")
+        .ok()
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(home)
+        .chain(
+            env::var("TERMINFO_DIRS")
+                .unwrap_or_default()
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .collect::<Vec<_>>(),
+        )
+        .chain(
+            ["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo"]
+                .into_iter()
+                .map(PathBuf::from),
+        );
+    for dir in dirs {
+        // Case-insensitive filesystems use hex-named directories instead of letters
+        for sub in [first.to_string(), format!("{:x}", u32::from(first))] {
+            if let Ok(data) = fs::read(dir.join(sub).join(term)) {
+                return parse_terminfo_colors(&data);
+            }
+        }
+    }
+    None
+}
+
+/// Reads `colors` (numeric capability 13) from a compiled terminfo entry, see `term(5)`
+fn parse_terminfo_colors(data: &[u8]) -> Option<u32> {
+    const COLORS: usize = 13;
+    let short = |offset: usize| -> Option<usize> {
+        Some(usize::from(u16::from_le_bytes(
+            *data.get(offset..offset + 2)?.first_chunk()?,
+        )))
+    };
+    let number_size = match short(0)? {
+        0o432 => 2,
+        0o1036 => 4,
+        _ => return None,
+    };
+    let names_size = short(2)?;
+    let bools_count = short(4)?;
+    let numbers_count = short(6)?;
+    if numbers_count <= COLORS {
+        return None;
+    }
+    // Numbers are aligned to an even byte
+    let numbers = (12 + names_size + bools_count).next_multiple_of(2);
+    let offset = numbers + COLORS * number_size;
+    let colors = data.get(offset..offset + number_size)?;
+    let colors = if number_size == 2 {
+        i32::from(i16::from_le_bytes(*colors.first_chunk()?))
+    } else {
+        i32::from_le_bytes(*colors.first_chunk()?)
+    };
+    u32::try_from(colors).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColorDepth, hash_multiplier, nearest_16, nearest_256, parse_terminfo_colors};
+
+    /// Builds a minimal compiled terminfo entry with given `colors`
+    fn terminfo(extended: bool, colors: i32) -> Vec<u8> {
+        let names = b"test|test terminal\0";
+        let mut data = vec![];
+        let magic: u16 = if extended { 0o1036 } else { 0o432 };
+        for short in [magic, 19, 1, 14, 0, 0] {
+            data.extend_from_slice(&short.to_le_bytes());
+        }
+        data.extend_from_slice(names);
+        data.push(1_u8); // one boolean
+        if data.len() % 2 == 1 {
+            data.push(0_u8); // alignment
+        }
+        for index in 0_usize..14 {
+            let value = if index == 13 { colors } else { -1_i32 };
+            if extended {
+                data.extend_from_slice(&value.to_le_bytes());
+            } else {
+                data.extend_from_slice(&i16::try_from(value).unwrap().to_le_bytes());
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn terminfo_colors() {
+        assert_eq!(
+            parse_terminfo_colors(&terminfo(false, 8)),
+            Some(8),
+            "legacy format"
+        );
+        assert_eq!(
+            parse_terminfo_colors(&terminfo(false, 256)),
+            Some(256),
+            "legacy format"
+        );
+        assert_eq!(
+            parse_terminfo_colors(&terminfo(true, 0x0100_0000)),
+            Some(0x0100_0000),
+            "extended number format"
+        );
+        assert_eq!(
+            parse_terminfo_colors(&terminfo(false, -1)),
+            None,
+            "absent capability"
+        );
+        assert_eq!(parse_terminfo_colors(b"garbage"), None, "not terminfo");
+    }
+
+    #[test]
+    fn depth_from_colors() {
+        assert_eq!(
+            ColorDepth::from_colors(0x0100_0000),
+            ColorDepth::TrueColor,
+            "direct"
+        );
+        assert_eq!(
+            ColorDepth::from_colors(256),
+            ColorDepth::Palette256,
+            "xterm-256color"
+        );
+        assert_eq!(
+            ColorDepth::from_colors(88),
+            ColorDepth::Palette16,
+            "xterm-88color"
+        );
+        assert_eq!(ColorDepth::from_colors(8), ColorDepth::Palette16, "linux");
+        assert_eq!(ColorDepth::from_colors(0), ColorDepth::Monochrome, "vt100");
+    }
+
+    #[test]
+    fn downsampling() {
+        assert_eq!(nearest_256((255, 0, 0)), 196, "pure red is in the cube");
+        assert_eq!(nearest_256((128, 128, 128)), 244, "gray is on the ramp");
+        assert_eq!(nearest_256((0, 0, 0)), 16, "black is in the cube");
+        assert_eq!(nearest_256((255, 255, 255)), 231, "white is in the cube");
+        assert_eq!(nearest_16((255, 80, 100)), 9, "light red");
+        assert_eq!(nearest_16((100, 255, 100)), 10, "light green");
+        assert_eq!(nearest_16((128, 128, 128)), 8, "gray");
+    }
+
+    #[test]
+    fn seeds() {
+        assert_eq!(hash_multiplier(0), 179, "default coloring is kept");
+        let residues = (0..22)
+            .map(|seed| hash_multiplier(seed) % 23)
+            .collect::<Vec<_>>();
+        for (seed, residue) in residues.iter().enumerate() {
+            assert_ne!(*residue, 0, "seed {seed} gives multiple of 23");
+            assert!(
+                !residues[..seed].contains(residue),
+                "seed {seed} repeats an earlier coloring"
+            );
+        }
+        assert_eq!(hash_multiplier(22), hash_multiplier(0), "seeds wrap around");
+    }
+}
@@ -10,28 +10,43 @@
 //! let bottom = default::bottom(&args);
 //!
 //! print!(
-//!     "{}\n{}{}",
+//!     "{}{}\n{}{}{}",
+//!     default::prompt_start().invisible(),
 //!     default::pretty(&top, &mode),
-//!     default::title(&args).invisible(),
-//!     default::pretty(&bottom, &mode)
+//!     (default::working_directory(&args) + &default::title(&args)).invisible(),
+//!     default::pretty(&bottom, &mode),
+//!     default::prompt_end().invisible()
 //! );
 //! ```
 
-use crate::{BlockType, Environment, Extend, IconMode, Pretty, Style as _};
-use std::borrow::Cow;
+use crate::{BlockType, Environment, Extend, IconMode, Mark, Pretty, Style as _};
+use chrono::Local;
+use std::{borrow::Cow, path::Path};
+
+/// Position of [`BlockType::Workdir`] in [`top`]. Workdir is moved to its own line when the top
+/// line does not fit into the terminal
+pub const TOP_WORKDIR: usize = 16;
 
 /// Default top part of statusline
 #[must_use]
-pub fn top(env: &Environment) -> [Box<dyn Extend>; 11] {
+pub fn top(env: &Environment) -> [Box<dyn Extend>; 19] {
     [
+        BlockType::Tier,
         BlockType::HostUser,
         BlockType::Ssh,
         BlockType::GitRepo,
         BlockType::GitTree,
         BlockType::BuildInfo,
+        BlockType::Toolchain,
         BlockType::Venv,
+        BlockType::DevEnv,
+        BlockType::Kube,
+        BlockType::Cloud,
         BlockType::Jobs,
+        BlockType::Load,
+        BlockType::Battery,
         BlockType::Mail,
+        BlockType::DiskFree,
         BlockType::Workdir,
         BlockType::Elapsed,
         BlockType::Time,
@@ -59,7 +74,7 @@ pub fn bottom(env: &Environment) -> [Box<dyn Extend>; 3] {
 
 /// Default title for statusline
 ///
-/// Shows username, hostname and current working directory
+/// Shows deployment tier, username, hostname and current working directory
 #[must_use]
 pub fn title(env: &Environment) -> String {
     let pwd = if let Some((home, user)) = &env.current_home {
@@ -77,9 +92,106 @@ pub fn title(env: &Environment) -> String {
     } else {
         Cow::from(env.work_dir.to_str().unwrap_or("<path>"))
     };
-    format!("{}@{}: {}", env.user, env.host, pwd)
-        .as_title()
+    let tier = env.tier.as_ref().map(|tier| format!("[{}] ", tier.label()));
+    format!(
+        "{}{}@{}: {}",
+        tier.unwrap_or_default(),
+        env.user,
+        env.host,
+        pwd
+    )
+    .as_title()
+    .to_string()
+}
+
+/// Terminal integration which goes before the top line: OSC 133 prompt start mark, so the top
+/// line is not taken for output of the previous command. Not visible, like [`title`]
+#[must_use]
+pub fn prompt_start() -> String {
+    "".with_mark(Mark::PromptStart).to_string()
+}
+
+/// Terminal integration which goes before the bottom line: OSC 7 working directory report. Not
+/// visible, like [`title`]
+#[must_use]
+pub fn working_directory(env: &Environment) -> String {
+    env.work_dir
+        .display()
+        .as_working_directory(&env.host)
+        .to_string()
+}
+
+/// Terminal integration which goes after the bottom line: OSC 133 mark of where the command is
+/// typed. Not visible, like [`title`]
+#[must_use]
+pub fn prompt_end() -> String {
+    "".with_mark(Mark::CommandStart).to_string()
+}
+
+/// Minimal one-line form of the top line: time, exit code and shortened working directory.
+/// Replaces the top line once the command is submitted, so scrollback stays compact
+#[must_use]
+pub fn transient(env: &Environment, mode: &IconMode) -> String {
+    let time = Local::now()
+        .format("%H:%M:%S")
         .to_string()
+        .visible()
+        .gray()
+        .with_reset()
+        .invisible()
+        .to_string();
+    let path = short_path(env)
+        .visible()
+        .cyan()
+        .with_reset()
+        .invisible()
+        .to_string();
+    [
+        Some(time),
+        BlockType::ReturnCode.create_from_env(env).pretty(mode),
+        Some(path),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// Working directory with home substituted, and all parents but the last shortened to their
+/// first letter, like `~/p/statusline`
+fn short_path(env: &Environment) -> String {
+    let (root, path) = match &env.current_home {
+        Some((home, user)) => (
+            format!("~{user}"),
+            env.work_dir.strip_prefix(home).unwrap_or(&env.work_dir),
+        ),
+        None => (String::new(), env.work_dir.as_path()),
+    };
+    let names = path
+        .iter()
+        .filter(|name| *name != Path::new("/"))
+        .map(|name| name.to_string_lossy())
+        .collect::<Vec<_>>();
+    let Some((last, parents)) = names.split_last() else {
+        return if root.is_empty() {
+            "/".to_owned()
+        } else {
+            root
+        };
+    };
+    let mut short = root;
+    for name in parents {
+        // Keep the dot of hidden directories, `.c` is more telling than `.`
+        let len = name
+            .char_indices()
+            .nth(usize::from(name.starts_with('.')) + 1)
+            .map_or(name.len(), |(i, _)| i);
+        short.push('/');
+        short.push_str(name.get(..len).unwrap_or(name));
+    }
+    short.push('/');
+    short.push_str(last);
+    short
 }
 
 /// Default pretty-printer
@@ -90,3 +202,41 @@ pub fn pretty<T: Pretty + ?Sized, const N: usize>(line: &[Box<T>; N], mode: &Ico
         .collect::<Vec<_>>()
         .join(" ")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::short_path;
+    use crate::{Chassis, Environment};
+    use std::path::PathBuf;
+
+    #[test]
+    fn short_paths() {
+        for (work_dir, home, expected) in [
+            ("/home/yuki/projects/statusline", Some(""), "~/p/statusline"),
+            ("/home/yuki/.config/nvim", Some(""), "~/.c/nvim"),
+            ("/home/yuki", Some(""), "~"),
+            ("/home/reimu/shrine", Some("reimu"), "~reimu/shrine"),
+            ("/usr/local/share/man", None, "/u/l/s/man"),
+            ("/", None, "/"),
+        ] {
+            let env = Environment {
+                ret_code: None,
+                pipe_status: vec![],
+                jobs_count: 0,
+                elapsed_time: None,
+                work_dir: PathBuf::from(work_dir),
+                git_tree: None,
+                user: "yuki".to_owned(),
+                host: "gensokyo".to_owned(),
+                chassis: Chassis::Unknown,
+                current_home: home.map(|user| {
+                    let owner = if user.is_empty() { "yuki" } else { user };
+                    (PathBuf::from("/home").join(owner), user.to_owned())
+                }),
+                kube: None,
+                tier: None,
+            };
+            assert_eq!(short_path(&env), expected, "{work_dir}");
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! Self-diagnostics, for `statusline doctor`
+//!
+//! Each check results in a single line of a checklist. Failed checks mean that the prompt is
+//! broken or looks wrong, warnings mean that something is probably misconfigured
+
+use crate::{
+    BlockType, Chassis, ColorDepth, Environment, IconMode, Probe, Style as _, virt,
+    workgroup::WorkgroupKey,
+};
+use rustix::{
+    event::{PollFd, PollFlags, poll},
+    fd::OwnedFd,
+    fs::{Mode, OFlags, open},
+    io::{read, write},
+    termios::{OptionalActions, tcgetattr, tcsetattr},
+};
+use std::{
+    env,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs,
+    io::ErrorKind,
+    os::unix::fs::PermissionsExt as _,
+    path::Path,
+    process::Command,
+    str,
+    time::{Duration, Instant},
+};
+use unicode_width::UnicodeWidthStr as _;
+
+/// Blocks slower than this make the prompt feel sluggish
+const SLOW_BLOCK: Duration = Duration::from_millis(50);
+
+/// Glyph used to probe how wide the terminal draws nerdfont icons
+const PROBE_GLYPH: &str = "\u{f418}";
+
+/// Check outcome
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Status {
+    /// Everything is fine
+    Ok,
+    /// Not a check, just something worth knowing
+    Info,
+    /// Probably misconfigured, but the prompt works
+    Warn,
+    /// The prompt is broken or looks wrong
+    Fail,
+    /// Could not be checked
+    Skip,
+}
+
+/// Single checklist line
+pub struct Check {
+    /// What was checked
+    pub name: String,
+    /// Outcome
+    pub status: Status,
+    /// Details, or a hint on how to fix the problem
+    pub detail: String,
+}
+
+impl Check {
+    fn new<N: Into<String>, D: Into<String>>(name: N, status: Status, detail: D) -> Self {
+        Check {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let label = match self.status {
+            Status::Ok => " ok ".boxed().green().with_reset().to_string(),
+            Status::Info => "info".boxed().blue().with_reset().to_string(),
+            Status::Warn => "warn".boxed().yellow().with_reset().to_string(),
+            Status::Fail => "FAIL".boxed().red().bold().with_reset().to_string(),
+            Status::Skip => "skip".boxed().gray().with_reset().to_string(),
+        };
+        write!(f, "{label} {}: {}", self.name, self.detail)
+    }
+}
+
+/// Runs all checks once, in checklist order
+#[must_use]
+pub fn checks(env: &Environment, mode: &IconMode) -> Vec<Check> {
+    let mut checks = vec![
+        shell_fd(),
+        prompt_command(),
+        git(),
+        truecolor(),
+        glyph_width(mode),
+        workgroup(),
+    ];
+    checks.extend(detection());
+    checks.extend(
+        BlockType::ALL
+            .iter()
+            .map(|kind| time_block(*kind, env, mode)),
+    );
+    checks
+}
+
+fn shell_fd() -> Check {
+    const NAME: &str = "shell integration";
+    let Ok(fd) = env::var("PS1_FD") else {
+        return Check::new(
+            NAME,
+            Status::Fail,
+            "PS1_FD is not set. Add `eval \"$(statusline env)\"` to ~/.bashrc and restart the shell",
+        );
+    };
+    if Path::new("/proc/self/fd").join(&fd).exists() {
+        Check::new(NAME, Status::Ok, format!("control fd {fd} is open"))
+    } else {
+        Check::new(
+            NAME,
+            Status::Fail,
+            format!("PS1_FD={fd}, but it is not open. Was it closed by something in ~/.bashrc?"),
+        )
+    }
+}
+
+fn prompt_command() -> Check {
+    const NAME: &str = "PROMPT_COMMAND";
+    // bash does not export PROMPT_COMMAND, so it is only seen if passed explicitly
+    match env::var(NAME) {
+        Ok(cmd) if cmd.contains("PS1_ELAPSED") => {
+            Check::new(NAME, Status::Ok, "measures elapsed time")
+        }
+        Ok(_) => Check::new(
+            NAME,
+            Status::Fail,
+            "was overwritten after statusline was loaded. Use `PROMPT_COMMAND+=...` to extend it",
+        ),
+        Err(_) => Check::new(
+            NAME,
+            Status::Skip,
+            "not exported, use `PROMPT_COMMAND=\"$PROMPT_COMMAND\" statusline doctor` to check it",
+        ),
+    }
+}
+
+fn git() -> Check {
+    const NAME: &str = "git";
+    match Command::new("git").arg("--version").output() {
+        Ok(output) if output.status.success() => Check::new(
+            NAME,
+            Status::Ok,
+            String::from_utf8_lossy(&output.stdout).trim(),
+        ),
+        Ok(output) => Check::new(
+            NAME,
+            Status::Fail,
+            format!("`git --version` failed with {}", output.status),
+        ),
+        Err(e) if e.kind() == ErrorKind::NotFound => Check::new(
+            NAME,
+            Status::Fail,
+            "not found in PATH, git status will not be shown",
+        ),
+        Err(e) => Check::new(NAME, Status::Fail, format!("could not be run: {e}")),
+    }
+}
+
+fn truecolor() -> Check {
+    const NAME: &str = "true color";
+    match ColorDepth::current() {
+        ColorDepth::TrueColor => Check::new(NAME, Status::Ok, "supported"),
+        ColorDepth::Monochrome => Check::new(
+            NAME,
+            Status::Info,
+            "colors are disabled by NO_COLOR, PS1_COLORS or TERM",
+        ),
+        depth => Check::new(
+            NAME,
+            Status::Warn,
+            format!(
+                "not supported, colors are approximated with {depth:?}. Set COLORTERM=truecolor \
+                 if the terminal can do better"
+            ),
+        ),
+    }
+}
+
+fn glyph_width(mode: &IconMode) -> Check {
+    const NAME: &str = "nerd font";
+    if matches!(mode, IconMode::Text) {
+        return Check::new(NAME, Status::Skip, "not needed with PS1_MODE=text");
+    }
+    let expected = PROBE_GLYPH.width();
+    match probe_width(PROBE_GLYPH) {
+        Some(width) if width == expected => Check::new(
+            NAME,
+            Status::Ok,
+            format!("icons are drawn {width} cell wide, as expected. Nerd font is likely"),
+        ),
+        Some(width) => Check::new(
+            NAME,
+            Status::Fail,
+            format!(
+                "icons are drawn {width} cells wide instead of {expected}, prompt will be \
+                 misaligned. Use a nerd font or PS1_MODE=text"
+            ),
+        ),
+        None => Check::new(
+            NAME,
+            Status::Skip,
+            "terminal did not report cursor position",
+        ),
+    }
+}
+
+/// Asks the terminal where the cursor is, returning the 1-based column
+fn cursor_column(tty: &OwnedFd) -> Option<usize> {
+    write(tty, b"\x1b[6n").ok()?;
+    let mut reply = vec![];
+    let mut buf = [0_u8; 32];
+    // Reply is `ESC [ row ; column R`
+    while !reply.ends_with(b"R") {
+        if poll(&mut [PollFd::new(tty, PollFlags::IN)], 300_i32).ok()? == 0 {
+            return None;
+        }
+        let len = read(tty, &mut buf).ok()?;
+        reply.extend_from_slice(buf.get(..len)?);
+        if len == 0 || reply.len() > 64 {
+            return None;
+        }
+    }
+    let (_, column) = str::from_utf8(&reply).ok()?.rsplit_once(';')?;
+    column.strip_suffix('R')?.parse().ok()
+}
+
+/// Prints a glyph to the controlling terminal to see how far the cursor moves, then erases it
+fn probe_width(glyph: &str) -> Option<usize> {
+    let tty = open("/dev/tty", OFlags::RDWR | OFlags::NOCTTY, Mode::empty()).ok()?;
+    let saved = tcgetattr(&tty).ok()?;
+    let mut raw = saved.clone();
+    raw.make_raw();
+    tcsetattr(&tty, OptionalActions::Now, &raw).ok()?;
+
+    let width = (|| {
+        write(&tty, b"\r").ok()?;
+        let start = cursor_column(&tty)?;
+        write(&tty, glyph.as_bytes()).ok()?;
+        cursor_column(&tty)?.checked_sub(start)
+    })();
+
+    write(&tty, b"\r\x1b[2K").unwrap_or_default();
+    tcsetattr(&tty, OptionalActions::Now, &saved).unwrap_or_default();
+    width
+}
+
+fn workgroup() -> Check {
+    const NAME: &str = "workgroup key";
+    let path = Path::new(&env::var("HOME").unwrap_or_default()).join(".ssh/workgroup");
+    let Ok(meta) = fs::metadata(&path) else {
+        return Check::new(
+            NAME,
+            Status::Skip,
+            "not created, ssh chains are not shown. `statusline create` makes one",
+        );
+    };
+    if let Err(e) = WorkgroupKey::load() {
+        return Check::new(NAME, Status::Fail, format!("{}: {e}", path.display()));
+    }
+    if meta.permissions().mode() & 0o077 != 0 {
+        return Check::new(
+            NAME,
+            Status::Warn,
+            format!("{} is readable by others, `chmod 600` it", path.display()),
+        );
+    }
+    Check::new(NAME, Status::Ok, path.display().to_string())
+}
+
+fn detection() -> [Check; 4] {
+    let probe = Probe::default();
+    let chassis = format!("{:?}", Chassis::detect(&probe));
+    let vm = match virt::detect_vm(&probe) {
+        Ok(vm) => vm.map_or("none", |vm| vm.name()).to_owned(),
+        Err(e) => format!("error: {e}"),
+    };
+    let container = match virt::detect_container(&probe) {
+        Ok(container) => container.map_or("none", |ct| ct.name()).to_owned(),
+        Err(e) => format!("error: {e}"),
+    };
+    let name = virt::container_name(&probe).unwrap_or_else(|| "none".to_owned());
+    [
+        Check::new("chassis", Status::Info, chassis),
+        Check::new("virtualization", Status::Info, vm),
+        Check::new("container", Status::Info, container),
+        Check::new("container name", Status::Info, name),
+    ]
+}
+
+fn time_block(kind: BlockType, env: &Environment, mode: &IconMode) -> Check {
+    let start = Instant::now();
+    let block = kind.create_from_env(env);
+    let created = start.elapsed();
+    let block = block.extend();
+    let extended = start.elapsed();
+    let shown = block.pretty(mode).is_some();
+    let total = start.elapsed();
+
+    let detail = format!(
+        "{total:.1?} (create {created:.1?}, extend {:.1?}, {})",
+        extended.saturating_sub(created),
+        if shown { "shown" } else { "hidden" }
+    );
+    let status = if total > SLOW_BLOCK {
+        Status::Warn
+    } else {
+        Status::Ok
+    };
+    Check::new(format!("block {kind:?}"), status, detail)
+}
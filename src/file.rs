@@ -1,10 +1,15 @@
 use anyhow::{Context as _, Result};
 use pwd::Passwd;
 use std::{
-    fs,
+    fs::{self, File},
+    io::Read as _,
     path::{Path, PathBuf},
+    process,
 };
 
+/// Largest project file read on every prompt. Anything bigger is not a config written by hand
+const READ_LIMIT: u64 = 1 << 20;
+
 #[must_use]
 pub fn find_current_home(path: &Path, cur_user: &str) -> Option<(PathBuf, String)> {
     if let Some(Passwd { name, dir, .. }) = Passwd::iter().find(|Passwd { dir, .. }| {
@@ -52,6 +57,14 @@ pub fn exists_that<F: Fn(&str) -> bool, P: AsRef<Path>>(path: P, f: F) -> Result
     Ok(false)
 }
 
+/// Ancestors of `start` up to `top`, inclusive. All of them if `start` is not inside `top`
+pub fn ancestors_until<'a>(start: &'a Path, top: Option<&Path>) -> impl Iterator<Item = &'a Path> {
+    let depth = top
+        .and_then(|top| start.strip_prefix(top).ok())
+        .map_or(usize::MAX, |rest| rest.components().count() + 1);
+    start.ancestors().take(depth)
+}
+
 pub fn upfind<P: AsRef<Path>>(start: P, filename: &str) -> Result<PathBuf> {
     start
         .as_ref()
@@ -60,3 +73,32 @@ pub fn upfind<P: AsRef<Path>>(start: P, filename: &str) -> Result<PathBuf> {
         .find(exists)
         .context("upfind could not find parent")
 }
+
+/// Reads a project file, unless it is larger than [`READ_LIMIT`]
+#[must_use]
+pub fn read_small(path: &Path) -> Option<String> {
+    let mut text = String::new();
+    File::open(path)
+        .ok()?
+        .take(READ_LIMIT + 1)
+        .read_to_string(&mut text)
+        .ok()?;
+    u64::try_from(text.len())
+        .is_ok_and(|len| len <= READ_LIMIT)
+        .then_some(text)
+}
+
+/// Replaces the file atomically, so concurrent prompts never see it half-written. Parent
+/// directories are created, and failures are ignored
+pub fn store(path: &Path, contents: &str) {
+    let Some(dir) = path.parent() else {
+        return;
+    };
+    let temp = path.with_extension(format!("{}.tmp", process::id()));
+    let stored = fs::create_dir_all(dir)
+        .and_then(|()| fs::write(&temp, contents))
+        .and_then(|()| fs::rename(&temp, path));
+    if stored.is_err() {
+        fs::remove_file(&temp).unwrap_or_default();
+    }
+}
@@ -0,0 +1,57 @@
+/// Matches text against a shell-like pattern, where `*` is any string and `?` is any character
+pub(crate) fn matches(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it currently covers up to
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, covered)) => {
+                    backtrack = Some((star, covered + 1));
+                    p = star + 1;
+                    t = covered + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern
+        .get(p..)
+        .is_some_and(|rest| rest.iter().all(|&c| c == '*'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn globs() {
+        for (pattern, text, expected) in [
+            ("prod-db-01", "prod-db-01", true),
+            ("prod-db-01", "prod-db-02", false),
+            ("*.prod.example.com", "db.prod.example.com", true),
+            ("*.prod.example.com", "prod.example.com", false),
+            ("staging-*", "staging-", true),
+            ("staging-*", "stage-1", false),
+            ("web-??", "web-01", true),
+            ("web-??", "web-1", false),
+            ("*a*b*", "xxaxxbxx", true),
+            ("*a*b", "xxaxxbxx", false),
+            ("*", "", true),
+            ("", "", true),
+            ("", "x", false),
+        ] {
+            assert_eq!(matches(pattern, text), expected, "{pattern:?} ~ {text:?}");
+        }
+    }
+}
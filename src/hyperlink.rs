@@ -0,0 +1,174 @@
+use std::{env, sync::OnceLock};
+
+static INSTALLED: OnceLock<Hyperlinks> = OnceLock::new();
+
+/// `TERM_PROGRAM` values of terminals which draw OSC 8 hyperlinks
+const TERM_PROGRAMS: [&str; 6] = ["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper", "rio"];
+
+/// Variables set by terminals which draw OSC 8 hyperlinks
+const TERM_VARIABLES: [&str; 5] = [
+    "KITTY_WINDOW_ID",
+    "WT_SESSION",
+    "KONSOLE_VERSION",
+    "ALACRITTY_WINDOW_ID",
+    "WEZTERM_PANE",
+];
+
+/// Whether OSC 8 hyperlinks are sent to the terminal
+///
+/// There is no way to ask the terminal whether it supports hyperlinks, so they are only sent to
+/// terminals which are known to draw them. Hyperlinks are sent until some other mode is
+/// [installed](Hyperlinks::install)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Hyperlinks {
+    Enabled,
+    Disabled,
+}
+
+impl Hyperlinks {
+    /// Detect hyperlink support of the terminal
+    ///
+    /// | Environment                                  | Result   |
+    /// |----------------------------------------------|----------|
+    /// | `PS1_HYPERLINKS=always\|never`                | As requested |
+    /// | `TERM_PROGRAM` of iTerm2, WezTerm, VS Code...| Enabled  |
+    /// | kitty, Windows Terminal, Konsole, alacritty  | Enabled  |
+    /// | `VTE_VERSION` is 0.50 or newer               | Enabled  |
+    /// | `TERM=foot*`                                 | Enabled  |
+    /// | otherwise, and inside tmux or screen         | Disabled |
+    #[must_use]
+    pub fn build() -> Self {
+        match env::var("PS1_HYPERLINKS").as_deref() {
+            Ok("always") => return Self::Enabled,
+            Ok("never") => return Self::Disabled,
+            _ => {}
+        }
+        if env::var_os("TMUX").is_some() || env::var_os("STY").is_some() {
+            return Self::Disabled;
+        }
+        let supported = env::var("TERM_PROGRAM")
+            .is_ok_and(|program| TERM_PROGRAMS.contains(&program.as_str()))
+            || TERM_VARIABLES.iter().any(|var| env::var_os(var).is_some())
+            || env::var("VTE_VERSION")
+                .ok()
+                .and_then(|version| version.parse::<u32>().ok())
+                .is_some_and(|version| version >= 5000)
+            || env::var("TERM").is_ok_and(|term| term.starts_with("foot"));
+        if supported {
+            Self::Enabled
+        } else {
+            Self::Disabled
+        }
+    }
+
+    /// Use this mode for all styles. Only the first call has an effect
+    pub fn install(self) {
+        INSTALLED.set(self).unwrap_or_default();
+    }
+
+    /// Installed mode, enabled by default
+    #[must_use]
+    pub fn current() -> Self {
+        INSTALLED.get().copied().unwrap_or(Self::Enabled)
+    }
+}
+
+/// Percent-encodes everything except unreserved characters and slashes, for URL paths
+pub(crate) fn percent_encode(path: &str) -> String {
+    path.bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+/// Web page of the branch on its forge, for GitHub, GitLab, Codeberg, Gitea and Forgejo remotes.
+/// Remote may be `https://host/owner/repo`, `ssh://git@host/owner/repo` or `git@host:owner/repo`
+pub(crate) fn branch_url(remote: &str, branch: &str) -> Option<String> {
+    let address = remote
+        .strip_prefix("https://")
+        .or_else(|| remote.strip_prefix("http://"))
+        .or_else(|| remote.strip_prefix("ssh://"))
+        .or_else(|| remote.strip_prefix("git+ssh://"));
+    let (host, path) = match address {
+        Some(address) => address.split_once('/')?,
+        // scp-like syntax, `user@host:path`
+        None => remote.split_once(':')?,
+    };
+    // Drop user and port
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let host = host.split_once(':').map_or(host, |(host, _)| host);
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+
+    let tree = if host == "github.com" {
+        "tree"
+    } else if host == "gitlab.com" || host.starts_with("gitlab.") {
+        "-/tree"
+    } else if host == "codeberg.org" || host.starts_with("gitea.") || host.starts_with("forgejo.") {
+        "src/branch"
+    } else {
+        return None;
+    };
+    Some(format!(
+        "https://{host}/{path}/{tree}/{}",
+        percent_encode(branch)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{branch_url, percent_encode};
+
+    #[test]
+    fn forges() {
+        for (remote, expected) in [
+            (
+                "https://github.com/yuki0iq/statusline.git",
+                Some("https://github.com/yuki0iq/statusline/tree/feat/x%2By"),
+            ),
+            (
+                "git@github.com:yuki0iq/statusline.git",
+                Some("https://github.com/yuki0iq/statusline/tree/feat/x%2By"),
+            ),
+            (
+                "ssh://git@gitlab.com:2222/group/sub/project.git",
+                Some("https://gitlab.com/group/sub/project/-/tree/feat/x%2By"),
+            ),
+            (
+                "https://codeberg.org/yuki0iq/statusline",
+                Some("https://codeberg.org/yuki0iq/statusline/src/branch/feat/x%2By"),
+            ),
+            (
+                "forgejo@gitea.example.com:team/app.git",
+                Some("https://gitea.example.com/team/app/src/branch/feat/x%2By"),
+            ),
+            ("https://example.com/some/repo.git", None),
+            ("/srv/git/repo.git", None),
+            ("../sibling", None),
+        ] {
+            assert_eq!(
+                branch_url(remote, "feat/x+y").as_deref(),
+                expected,
+                "{remote}"
+            );
+        }
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(
+            percent_encode("/home/yuki/мои заметки/50%"),
+            "/home/yuki/%D0%BC%D0%BE%D0%B8%20%D0%B7%D0%B0%D0%BC%D0%B5%D1%82%D0%BA%D0%B8/50%25",
+            "non-ascii, spaces and percents"
+        );
+    }
+}
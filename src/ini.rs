@@ -0,0 +1,78 @@
+/// Reads `key = value` from `[section]` of an INI-like file, as AWS and gcloud CLIs write them.
+/// Keys before the first section are in the section with empty name
+pub(crate) fn value(text: &str, section: &str, key: &str) -> Option<String> {
+    entries(text, section)
+        .find(|(name, _)| *name == key)
+        .map(|(_, value)| value.to_owned())
+}
+
+/// All `key = value` pairs of `[section]`, in file order
+pub(crate) fn entries<'a>(
+    text: &'a str,
+    section: &'a str,
+) -> impl Iterator<Item = (&'a str, &'a str)> {
+    let mut current = "";
+    text.lines().filter_map(move |line| {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            return None;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            current = name.trim();
+            return None;
+        }
+        if current != section {
+            return None;
+        }
+        let (name, value) = line.split_once('=')?;
+        Some((name.trim(), value.trim()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{entries, value};
+
+    #[test]
+    fn sections() {
+        let text = "\
+top = level
+[default]
+region = us-east-1
+
+# comment
+[profile prod]
+output=json
+region = eu-west-1
+[core]
+project = my-project
+";
+        assert_eq!(
+            value(text, "default", "region").as_deref(),
+            Some("us-east-1"),
+            "default"
+        );
+        assert_eq!(
+            value(text, "profile prod", "region").as_deref(),
+            Some("eu-west-1"),
+            "profile"
+        );
+        assert_eq!(
+            value(text, "core", "project").as_deref(),
+            Some("my-project"),
+            "gcloud"
+        );
+        assert_eq!(value(text, "profile dev", "region"), None, "no section");
+        assert_eq!(value(text, "core", "account"), None, "no key");
+        assert_eq!(
+            value(text, "", "top").as_deref(),
+            Some("level"),
+            "before sections"
+        );
+        assert_eq!(
+            entries(text, "profile prod").collect::<Vec<_>>(),
+            [("output", "json"), ("region", "eu-west-1")],
+            "all entries"
+        );
+    }
+}
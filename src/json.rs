@@ -0,0 +1,225 @@
+use std::{iter::Peekable, str::Chars};
+
+/// Arrays and objects nested deeper are rejected, so hostile files can not overflow the stack
+const MAX_DEPTH: usize = 128;
+
+/// Parsed JSON value. Only what is needed to read CLI tool configs, without any dependencies
+#[derive(Debug, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses a JSON document, which may start with a byte order mark
+    pub(crate) fn parse(text: &str) -> Option<Json> {
+        let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+        let value = parse_value(&mut chars, 0)?;
+        skip_whitespace(&mut chars);
+        chars.peek().is_none().then_some(value)
+    }
+
+    /// Object member by key
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_object(&self) -> Option<&[(String, Json)]> {
+        match self {
+            Json::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(char::is_ascii_whitespace).is_some() {}
+}
+
+fn parse_value(chars: &mut Peekable<Chars>, depth: usize) -> Option<Json> {
+    skip_whitespace(chars);
+    let next = *chars.peek()?;
+    if matches!(next, '{' | '[') && depth >= MAX_DEPTH {
+        return None;
+    }
+    match next {
+        '{' => {
+            chars.next();
+            let mut members = vec![];
+            skip_whitespace(chars);
+            if chars.next_if_eq(&'}').is_some() {
+                return Some(Json::Object(members));
+            }
+            loop {
+                skip_whitespace(chars);
+                let Json::String(key) = parse_value(chars, depth + 1)? else {
+                    return None;
+                };
+                skip_whitespace(chars);
+                chars.next_if_eq(&':')?;
+                members.push((key, parse_value(chars, depth + 1)?));
+                skip_whitespace(chars);
+                match chars.next()? {
+                    ',' => {}
+                    '}' => return Some(Json::Object(members)),
+                    _ => return None,
+                }
+            }
+        }
+        '[' => {
+            chars.next();
+            let mut values = vec![];
+            skip_whitespace(chars);
+            if chars.next_if_eq(&']').is_some() {
+                return Some(Json::Array(values));
+            }
+            loop {
+                values.push(parse_value(chars, depth + 1)?);
+                skip_whitespace(chars);
+                match chars.next()? {
+                    ',' => {}
+                    ']' => return Some(Json::Array(values)),
+                    _ => return None,
+                }
+            }
+        }
+        '"' => {
+            chars.next();
+            parse_string(chars).map(Json::String)
+        }
+        't' => parse_literal(chars, "true", Json::Bool(true)),
+        'f' => parse_literal(chars, "false", Json::Bool(false)),
+        'n' => parse_literal(chars, "null", Json::Null),
+        _ => {
+            let mut number = String::new();
+            while let Some(c) =
+                chars.next_if(|c| matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+            {
+                number.push(c);
+            }
+            number.parse().ok().map(Json::Number)
+        }
+    }
+}
+
+fn parse_literal(chars: &mut Peekable<Chars>, literal: &str, value: Json) -> Option<Json> {
+    for expected in literal.chars() {
+        chars.next_if_eq(&expected)?;
+    }
+    Some(value)
+}
+
+/// Parses string contents after the opening quote
+fn parse_string(chars: &mut Peekable<Chars>) -> Option<String> {
+    let mut value = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                'b' => value.push('\u{8}'),
+                'f' => value.push('\u{c}'),
+                'u' => {
+                    let mut code = parse_hex4(chars)?;
+                    // Characters outside of BMP are encoded as surrogate pairs
+                    if (0xd800..0xdc00).contains(&code) {
+                        chars.next_if_eq(&'\\')?;
+                        chars.next_if_eq(&'u')?;
+                        let low = parse_hex4(chars)?;
+                        code =
+                            0x1_0000 + ((code - 0xd800) << 10_u32) + (low.checked_sub(0xdc00)?);
+                    }
+                    value.push(char::from_u32(code)?);
+                }
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
+    }
+}
+
+fn parse_hex4(chars: &mut Peekable<Chars>) -> Option<u32> {
+    let mut code = 0;
+    for _ in 0..4_u8 {
+        code = code * 16 + chars.next()?.to_digit(16)?;
+    }
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+
+    #[test]
+    fn azure_profile() {
+        let text = "\u{feff}{\"installationId\": \"x\", \"subscriptions\": [
+            {\"id\": \"1\", \"name\": \"Dev \\u00e9\\ud83d\\ude00\", \"isDefault\": false, \"tenantId\": null},
+            {\"id\": \"2\", \"name\": \"Prod\", \"isDefault\": true, \"limits\": [1, -2.5e3]}
+        ]}";
+        let json = Json::parse(text).expect("profile should be parsed");
+        let subscriptions = json
+            .get("subscriptions")
+            .and_then(Json::as_array)
+            .expect("subscriptions should be an array");
+        assert_eq!(subscriptions.len(), 2, "two subscriptions");
+        assert_eq!(
+            subscriptions[0].get("name").and_then(Json::as_str),
+            Some("Dev \u{e9}\u{1f600}"),
+            "escapes"
+        );
+        assert_eq!(
+            subscriptions[1].get("isDefault").and_then(Json::as_bool),
+            Some(true),
+            "bool"
+        );
+        assert_eq!(
+            subscriptions[1].get("limits"),
+            Some(&Json::Array(vec![Json::Number(1.0), Json::Number(-2500.0)])),
+            "numbers"
+        );
+        assert_eq!(Json::parse("{\"a\": }"), None, "malformed");
+        assert_eq!(Json::parse("[] []"), None, "trailing data");
+    }
+
+    #[test]
+    fn nesting() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(128)).is_some(), "deep enough");
+        assert_eq!(Json::parse(&nested(129)), None, "too deep");
+        let hostile = format!("{{\"scripts\": {}}}", nested(200_000));
+        assert_eq!(Json::parse(&hostile), None, "no stack overflow");
+    }
+}
@@ -100,19 +100,34 @@
 mod args;
 mod block;
 mod chassis;
+mod color;
+mod glob;
+mod hyperlink;
 mod icon;
+mod ini;
+mod json;
+mod probe;
 mod style;
 mod time;
-mod virt;
 
+pub mod cancel;
 pub mod default;
+pub mod doctor;
 pub mod file;
+pub mod notify;
+pub mod painted;
+pub mod targets;
+pub mod virt;
 pub mod workgroup;
 
 pub use crate::{
     args::Environment,
-    block::{Extend, Kind as BlockType},
+    block::{Extend, Kind as BlockType, kube::Kube, tier::Tier},
     chassis::Chassis,
+    color::{Color, ColorDepth, ColorRules},
+    hyperlink::Hyperlinks,
     icon::{Icon, IconMode, Pretty},
-    style::{Style, Styled},
+    probe::Probe,
+    style::{Mark, Style, Styled},
+    virt::{ContainerType, VirtualizationType},
 };
@@ -1,27 +1,159 @@
 use argh::FromArgs;
 use rustix::{
-    fd::{self, AsRawFd, FromRawFd},
-    fs as rfs, process, stdio,
+    fd::{self, FromRawFd},
+    fs as rfs, stdio,
 };
 use statusline::{
-    BlockType, Chassis, Environment, IconMode, Style, default, file,
+    BlockType, Chassis, ColorDepth, ColorRules, Environment, Hyperlinks, IconMode, Kube, Pretty,
+    Probe, Style, Tier,
+    cancel::{self, Typing},
+    default,
+    doctor::{self, Status},
+    file, notify,
+    painted::{self, Painted},
+    targets, virt,
     workgroup::{SshChain, WorkgroupKey},
 };
-use std::{env, fs, io, io::Write, path::PathBuf};
+use std::{env, fs, io, io::Write, iter, path::PathBuf};
 use unicode_width::UnicodeWidthStr;
 
+/// Width of the text outside of `\x01` and `\x02` markers, which are not shown either
 fn readline_width(s: &str) -> usize {
     let mut res = s.width();
     for (i, c) in s.bytes().enumerate() {
         match c {
-            b'\x01' => res += i + 1,
-            b'\x02' => res -= i,
+            b'\x01' => res += i,
+            b'\x02' => res -= i + 1,
             _ => {}
         }
     }
     res
 }
 
+fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(w, _h)| w.0)
+        .unwrap_or(80)
+        .into()
+}
+
+/// Whether the top line is too long to share its line with the working directory
+fn is_too_long<T: Pretty + ?Sized>(line: &[Box<T>], mode: &IconMode) -> bool {
+    let line_length: usize = line
+        .iter()
+        .filter_map(|x| x.pretty(mode))
+        .map(|x| readline_width(&x))
+        .sum();
+    line_length + 16 >= terminal_width()
+}
+
+/// Rows above the bottom line: the top line, and a continuation row with the working directory if
+/// the top line is too long, and with build targets if there are any
+fn top_rows<T: Pretty + ?Sized>(
+    args: &Environment,
+    line: &[Box<T>],
+    targets: &T,
+    too_long: bool,
+    mode: &IconMode,
+) -> Vec<String> {
+    let top = line
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| !too_long || i != default::TOP_WORKDIR)
+        .filter_map(|(_, block)| block.pretty(mode))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let targets = targets.pretty(mode);
+    if !too_long && targets.is_none() {
+        return vec![top];
+    }
+    let workdir = line
+        .get(default::TOP_WORKDIR)
+        .filter(|_| too_long)
+        .and_then(|block| block.pretty(mode));
+    let second = [
+        BlockType::Continue.create_from_env(args).pretty(mode),
+        workdir,
+        targets,
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" ");
+    vec![top, second]
+}
+
+/// Paints rows above the bottom line, where the cursor is, over the `before` terminal rows of the
+/// previous paint. The old rows are deleted and `now` empty ones are inserted for the new rows,
+/// so nothing is left of rows which wrapped. When more rows are needed, the screen is scrolled up
+/// to make room for them, so the bottom line stays where readline has drawn it. The prompt start
+/// mark goes with the top row, since deleting the rows takes their marks away
+fn paint_rows(rows: &[String], now: usize, before: usize) -> String {
+    let rows = default::prompt_start()
+        + &rows
+            .iter()
+            .map(|row| row.clear_till_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+    let count = |rows: usize| i32::try_from(rows).unwrap_or(i32::MAX);
+    let (scroll, pull) = (now.saturating_sub(before), before.saturating_sub(now));
+    let rows = rows
+        .insert_line(count(now))
+        .delete_line(count(before))
+        .prev_line(count(before + scroll))
+        .save_restore()
+        .to_string();
+    match (scroll, pull) {
+        (0, 0) => rows,
+        (0, _) => format!("{rows}{}", "".cursor_up(count(pull))),
+        _ => format!("{}{rows}", "".scroll_up(scroll).save_restore()),
+    }
+}
+
+/// [`paint_rows`] once the bottom line is drawn. The prompt start mark of the top row makes
+/// terminals take what follows for the prompt, so the command start mark is set again where the
+/// cursor is back
+fn repaint_rows(rows: &[String], now: usize, before: usize) -> String {
+    paint_rows(rows, now, before) + &default::prompt_end()
+}
+
+/// Widths of the painted rows. The top row ends with the time, which is aligned to the last but
+/// one column whatever is before it
+fn widths(rows: &[String]) -> Painted {
+    let top = terminal_width().saturating_sub(1);
+    let widths = rows.iter().skip(1).map(|row| readline_width(row));
+    Painted::new(iter::once(top).chain(widths).collect())
+}
+
+/// Records the painted rows for `transient`
+fn record(painted: &Painted) {
+    if let Some(control) = cancel::control() {
+        painted.save(control);
+    }
+}
+
+/// Command from `history 1` output, like `  42  make test`
+fn history_entry(line: &str) -> &str {
+    let line = line
+        .trim_start()
+        .trim_start_matches(|c: char| c.is_ascii_digit());
+    let line = line.strip_prefix('*').unwrap_or(line);
+    line.strip_prefix("  ").unwrap_or(line)
+}
+
+/// Rows taken by the command typed after the prompt, including the line break after it.
+/// Continuation lines are counted with the default `> ` prompt
+fn command_rows(prompt_width: usize, command: &str, columns: usize) -> usize {
+    command
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            let prompt_width = if i == 0 { prompt_width } else { 2 };
+            (prompt_width + line.width()) / columns.max(1) + 1
+        })
+        .sum()
+}
+
 #[derive(FromArgs)]
 /// statusline
 struct Arguments {
@@ -41,6 +173,10 @@ enum Command {
     Chain(Chain),
     Run(Run),
     Env(Env),
+    Detect(Detect),
+    Doctor(Doctor),
+    Targets(Targets),
+    Transient(Transient),
 }
 
 #[derive(FromArgs)]
@@ -48,6 +184,49 @@ enum Command {
 /// print bash commands
 struct Env {}
 
+#[derive(FromArgs)]
+#[argh(subcommand, name = "detect")]
+/// print detected chassis, virtualization and container, and why
+struct Detect {
+    #[argh(option, default = "PathBuf::from(\"/\")")]
+    /// filesystem root to detect from, like a copy of another machine's files
+    root: PathBuf,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "doctor")]
+/// check if statusline is set up properly
+struct Doctor {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "targets")]
+/// list makefile, justfile, package.json and cargo alias targets
+struct Targets {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "transient")]
+/// collapse the top line of the submitted prompt
+struct Transient {
+    #[argh(option)]
+    /// return code shown in the prompt
+    return_code: Option<u8>,
+    #[argh(option)]
+    /// return codes of the last pipeline, space-separated
+    pipe_status: Option<String>,
+    #[argh(option)]
+    /// background jobs count
+    jobs_count: usize,
+    #[argh(option)]
+    /// elapsed time shown in the prompt, in seconds
+    elapsed_time: Option<u64>,
+    #[argh(option)]
+    /// submitted command, as printed by `history 1`
+    command: Option<String>,
+    #[argh(option)]
+    /// control pipe of the submitted prompt, which is cancelled
+    control_fd: Option<i32>,
+}
+
 #[derive(FromArgs)]
 #[argh(subcommand, name = "chain")]
 /// append this host to chain
@@ -60,11 +239,14 @@ struct WorkgroupCreate {}
 
 #[derive(FromArgs)]
 #[argh(subcommand, name = "colorize")]
-/// colorize as username
+/// colorize as username, or preview color rules
 struct Colorize {
     #[argh(option)]
-    /// what to colorize
-    what: String,
+    /// what to colorize, may be repeated. Configured rules are shown if not given
+    what: Vec<String>,
+    #[argh(option)]
+    /// hash seed to try instead of PS1_COLOR_SEED
+    seed: Option<usize>,
 }
 
 #[derive(FromArgs)]
@@ -75,19 +257,34 @@ struct Run {
     /// return code to show
     return_code: Option<u8>,
     #[argh(option)]
+    /// return codes of the last pipeline, space-separated
+    pipe_status: Option<String>,
+    #[argh(option)]
     /// current background jobs count
     jobs_count: usize,
     #[argh(option)]
     /// elapsed time to show, in seconds
     elapsed_time: Option<u64>,
+    #[argh(switch)]
+    /// notify about long commands without asking the terminal whether it is focused
+    notify_always: bool,
+    #[argh(option)]
+    /// shell which reads the typed command, repaint on resize stops once it has read anything
+    shell_pid: Option<i32>,
     #[argh(option)]
-    /// control pid for terminating
+    /// control pipe, the extended render is cancelled once something is written to it
     control_fd: Option<i32>,
 }
 
 impl From<Run> for Environment {
     fn from(other: Run) -> Environment {
         let ret_code = other.return_code;
+        let pipe_status = other
+            .pipe_status
+            .unwrap_or_default()
+            .split_whitespace()
+            .filter_map(|code| code.parse().ok())
+            .collect();
         let jobs_count = other.jobs_count;
         let elapsed_time = other.elapsed_time;
 
@@ -107,8 +304,9 @@ impl From<Run> for Environment {
 
         let current_home = file::find_current_home(&work_dir, &user);
 
-        Environment {
+        let mut env = Environment {
             ret_code,
+            pipe_status,
             jobs_count,
             elapsed_time,
             git_tree,
@@ -117,11 +315,20 @@ impl From<Run> for Environment {
             host,
             chassis,
             current_home,
-        }
+            kube: None,
+            tier: None,
+        };
+        env.kube = Kube::detect(&env.work_dir, env.project_top());
+        env.tier = Tier::detect(&env.host, env.kube.as_ref());
+        env
     }
 }
 
 fn main() {
+    ColorDepth::build().install();
+    ColorRules::build().install();
+    Hyperlinks::build().install();
+
     let exec = fs::read_link("/proc/self/exe")
         .map(|pb| String::from(pb.to_string_lossy()))
         .unwrap_or("<executable>".to_owned());
@@ -159,10 +366,143 @@ fn main() {
     };
 
     match command {
-        Command::Colorize(Colorize { what }) => println!("{}", what.colorize_with(&what).bold()),
+        Command::Colorize(Colorize { what, seed }) => {
+            let rules = ColorRules::build();
+            let rules = match seed {
+                Some(seed) => rules.with_seed(seed),
+                None => rules,
+            };
+            if what.is_empty() {
+                println!("Hash seed: {}", rules.seed());
+                if rules.rules().next().is_none() {
+                    println!("No rules in PS1_COLOR_RULES");
+                }
+                for (pattern, color) in rules.rules() {
+                    println!("{}", pattern.with_color(color).bold().with_reset());
+                }
+            }
+            for what in &what {
+                let color = rules.color_for(what);
+                println!("{}", what.with_color(color).bold().with_reset());
+            }
+        }
         Command::WorkgroupCreate(_) => {
             WorkgroupKey::create().expect("Could not create workgroup key")
         }
+        Command::Detect(Detect { root }) => {
+            let report = |what: &str, found: String, probe: Probe| {
+                println!("{what}: {found}");
+                for why in probe.notes() {
+                    println!("    {why}");
+                }
+            };
+
+            let probe = Probe::new(&root);
+            report("chassis", format!("{:?}", Chassis::detect(&probe)), probe);
+
+            let probe = Probe::new(&root);
+            let found = match virt::detect_vm(&probe) {
+                Ok(vm) => vm.map_or("none", |vm| vm.name()).to_owned(),
+                Err(e) => format!("error: {e}"),
+            };
+            report("virtualization", found, probe);
+
+            let probe = Probe::new(&root);
+            let found = match virt::detect_container(&probe) {
+                Ok(container) => container.map_or("none", |ct| ct.name()).to_owned(),
+                Err(e) => format!("error: {e}"),
+            };
+            report("container", found, probe);
+
+            let probe = Probe::new(&root);
+            let found = virt::container_name(&probe).unwrap_or_else(|| "none".to_owned());
+            report("container name", found, probe);
+        }
+        Command::Doctor(_) => {
+            let args = Run {
+                return_code: None,
+                pipe_status: None,
+                jobs_count: 0,
+                elapsed_time: None,
+                notify_always: false,
+                shell_pid: None,
+                control_fd: None,
+            }
+            .into();
+            let checks = doctor::checks(&args, &IconMode::build());
+            for check in &checks {
+                println!("{check}");
+            }
+            if checks.iter().any(|check| check.status == Status::Fail) {
+                std::process::exit(1);
+            }
+        }
+        Command::Targets(_) => {
+            let work_dir = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+            for source in targets::find(&work_dir) {
+                println!("{} ({})", source.tool, source.path.display());
+                for target in source.targets {
+                    println!("    {target}");
+                }
+            }
+        }
+        Command::Transient(transient) => {
+            let Some(fd) = transient.control_fd else {
+                return;
+            };
+            // SAFETY: the shell opens control fd for this process only
+            let control = unsafe { fd::OwnedFd::from_raw_fd(fd) };
+            // Rows as `run` has painted them, which stops painting once cancelled
+            let Some(painted) = Painted::load(&control) else {
+                return;
+            };
+            rustix::io::write(&control, b"\n").unwrap_or_default();
+
+            let mode = IconMode::build();
+            let command = transient.command.unwrap_or_default();
+            let args: Environment = Run {
+                return_code: transient.return_code,
+                pipe_status: transient.pipe_status,
+                jobs_count: transient.jobs_count,
+                elapsed_time: transient.elapsed_time,
+                notify_always: false,
+                shell_pid: None,
+                control_fd: None,
+            }
+            .into();
+
+            // The cursor is on the line after the command
+            let columns = terminal_width();
+            let above = painted.rows(columns, painted::rewraps());
+            let prompt_width = readline_width(&default::pretty(&default::bottom(&args), &mode));
+            let up = command_rows(prompt_width, history_entry(&command), columns) + above;
+
+            let height = terminal_size::terminal_size().map_or(24, |(_w, h)| usize::from(h.0));
+            // Top line has gone to scrollback, where cursor can not reach
+            let Some(up) = i32::try_from(up).ok().filter(|_| up < height) else {
+                return;
+            };
+            let extra = i32::try_from(above.saturating_sub(1)).unwrap_or_default();
+
+            let minimal = default::transient(&args, &mode);
+            cancel::paint(&if extra > 0 {
+                format!(
+                    "{}{}",
+                    minimal
+                        .clear_till_end()
+                        .join_lf("".delete_line(extra).to_string())
+                        .prev_line(up)
+                        .save_restore(),
+                    "".prev_line(extra)
+                )
+            } else {
+                minimal
+                    .clear_till_end()
+                    .prev_line(up)
+                    .save_restore()
+                    .to_string()
+            });
+        }
         Command::Env(_) => println!("{}", include_str!("shell.sh").replace("<exec>", &exec)),
         Command::Chain(_) => {
             let Ok(key) = WorkgroupKey::load() else {
@@ -179,102 +519,86 @@ fn main() {
         }
         Command::Run(run) => {
             if let Some(fd) = run.control_fd {
-                let controlling_fd = unsafe { fd::OwnedFd::from_raw_fd(fd) };
-                unsafe {
-                    libc::fcntl(
-                        controlling_fd.as_raw_fd(),
-                        libc::F_SETOWN,
-                        process::getpid(),
-                    )
-                };
-                let _ = rfs::fcntl_setfl(
-                    controlling_fd,
-                    rfs::OFlags::from_bits_retain(libc::O_ASYNC as u32),
-                );
+                // SAFETY: the shell opens control fd for this process only
+                cancel::install(unsafe { fd::OwnedFd::from_raw_fd(fd) });
             }
 
             let mode = IconMode::build();
+            let notify_always = run.notify_always;
+            let shell_pid = run.shell_pid;
             let args = run.into();
+            if let Some(notification) = notify::notification(&args, notify_always) {
+                eprint!("{notification}");
+            }
             let bottom = default::bottom(&args);
 
-            let mut line = default::top(&args);
-
-            let line_length: usize = line
-                .iter()
-                .filter_map(|x| x.pretty(&mode))
-                .map(|x| readline_width(&x))
-                .sum();
-
-            if line_length + 16
-                >= terminal_size::terminal_size()
-                    .map(|(w, _h)| w.0)
-                    .unwrap_or(80)
-                    .into()
-            {
-                // three lines
-                let mut second = BlockType::Empty.create_from_env(&args);
-                std::mem::swap(&mut second, &mut line[8]);
-                let second = [BlockType::Continue.create_from_env(&args), second];
-
-                eprint!(
-                    "\n\n\n{}",
-                    default::pretty(&line, &mode)
-                        .join_lf(default::pretty(&second, &mode))
-                        .clear_till_end()
-                        .prev_line(2)
-                        .save_restore()
-                );
+            let line = default::top(&args);
+            let targets = BlockType::Targets.create_from_env(&args);
+            let too_long = is_too_long(&line, &mode);
+            let rows = top_rows(&args, &line, &*targets, too_long, &mode);
+            let rewrap = painted::rewraps();
+            let mut painted = widths(&rows);
+            let now = painted.rows(terminal_width(), rewrap);
+            cancel::paint(&format!(
+                "{}{}",
+                "\n".repeat(now + 1),
+                paint_rows(&rows, now, now)
+            ));
+            record(&painted);
 
-                print!(
-                    "{}{}",
-                    default::title(&args).invisible(),
-                    default::pretty(&bottom, &mode)
-                );
-                io::stdout().flush().unwrap();
-                stdio::dup2_stdout(
-                    rfs::open("/dev/null", rfs::OFlags::RDWR, rfs::Mode::empty()).unwrap(),
-                )
-                .unwrap();
-
-                let line = default::extend(line);
-                let second = default::extend(second);
-                eprint!(
-                    "{}",
-                    default::pretty(&line, &mode)
-                        .join_lf(default::pretty(&second, &mode))
-                        .clear_till_end()
-                        .prev_line(2)
-                        .save_restore()
-                );
-            } else {
-                // two lines
-                eprint!(
-                    "\n\n{}",
-                    default::pretty(&line, &mode)
-                        .clear_till_end()
-                        .prev_line(1)
-                        .save_restore()
-                );
+            print!(
+                "{}{}{}",
+                (default::working_directory(&args) + &default::title(&args)).invisible(),
+                default::pretty(&bottom, &mode),
+                default::prompt_end().invisible()
+            );
+            io::stdout().flush().unwrap();
+            // Once the shell has read the prompt, whatever else it reads is typed
+            let typing = shell_pid
+                .filter(|_| cancel::drained(io::stdout()))
+                .and_then(Typing::watch);
+            stdio::dup2_stdout(
+                rfs::open("/dev/null", rfs::OFlags::RDWR, rfs::Mode::empty()).unwrap(),
+            )
+            .unwrap();
 
-                print!(
-                    "{}{}",
-                    default::title(&args).invisible(),
-                    default::pretty(&bottom, &mode)
-                );
-                io::stdout().flush().unwrap();
-                stdio::dup2_stdout(
-                    rfs::open("/dev/null", rfs::OFlags::RDWR, rfs::Mode::empty()).unwrap(),
-                )
-                .unwrap();
+            let line = default::extend(line);
+            let targets = targets.extend();
+            let rows = top_rows(&args, &line, &*targets, too_long, &mode);
+            let extended = widths(&rows);
+            let columns = terminal_width();
+            cancel::paint(&repaint_rows(
+                &rows,
+                extended.rows(columns, rewrap),
+                painted.rows(columns, rewrap),
+            ));
+            painted = extended;
+            record(&painted);
 
-                let line = default::extend(line);
-                eprint!(
-                    "{}",
-                    default::pretty(&line, &mode)
-                        .clear_till_end()
-                        .prev_line(1)
-                        .save_restore()
-                );
+            // Stay until the prompt is gone, and repaint with new width. Rows are painted relative
+            // to the cursor, which stays on the prompt row only until something is typed
+            let mut width = terminal_width();
+            cancel::on_resize(|| {
+                if terminal_width() == width {
+                    return;
+                }
+                width = terminal_width();
+                if typing.as_ref().is_none_or(Typing::started) {
+                    return;
+                }
+                let too_long = is_too_long(&line, &mode);
+                let rows = top_rows(&args, &line, &*targets, too_long, &mode);
+                let resized = widths(&rows);
+                cancel::paint(&repaint_rows(
+                    &rows,
+                    resized.rows(width, rewrap),
+                    painted.rows(width, rewrap),
+                ));
+                painted = resized;
+                record(&painted);
+            });
+            if let Some(control) = cancel::control() {
+                Painted::remove(control);
             }
         }
     }
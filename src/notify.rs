@@ -0,0 +1,159 @@
+//! Desktop notification when a long-running command finishes
+//!
+//! Sent with OSC 9 or OSC 777 escape sequences, or just a bell, only when the terminal is not
+//! focused. Focus is asked with xterm focus reporting, which not every terminal answers
+
+use crate::{Environment, Style as _, time};
+use rustix::{
+    event::{PollFd, PollFlags, poll},
+    fs::{Mode, OFlags, open},
+    io::{ioctl_fionread, read, write},
+    termios::{OptionalActions, tcgetattr, tcsetattr},
+};
+use std::env;
+
+/// How to ask the terminal for attention
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Protocol {
+    /// `OSC 9 ; body`, from iTerm, also known to Windows Terminal, kitty and wezterm
+    Osc9,
+    /// `OSC 777 ; notify ; title ; body`, from rxvt-unicode, also known to VTE, foot and wezterm
+    Osc777,
+    /// Plain BEL, which most terminals turn into an urgency hint
+    Bell,
+}
+
+impl Protocol {
+    fn from_name(name: &str) -> Option<Protocol> {
+        Some(match name.trim() {
+            "osc9" | "9" => Self::Osc9,
+            "osc777" | "777" => Self::Osc777,
+            "bell" | "bel" => Self::Bell,
+            _ => None?,
+        })
+    }
+
+    /// Protocols from `PS1_NOTIFY`, like `osc777,bell`
+    fn configured() -> Vec<Protocol> {
+        env::var("PS1_NOTIFY")
+            .unwrap_or("osc9,bell".to_owned())
+            .split(',')
+            .filter_map(Protocol::from_name)
+            .collect()
+    }
+}
+
+/// Whether it is worth notifying. Unknown focus counts as unfocused, since the user asked for
+/// notifications by setting the threshold
+fn should_notify(
+    elapsed: u64,
+    threshold: Option<u64>,
+    focused: impl FnOnce() -> Option<bool>,
+) -> bool {
+    threshold.is_some_and(|threshold| elapsed >= threshold) && focused() != Some(true)
+}
+
+/// Notification escape sequences for the last command, if it took longer than
+/// `PS1_NOTIFY_THRESHOLD` seconds while the terminal was not focused. Focus is not asked when
+/// `always` is set
+#[must_use]
+pub fn notification(env: &Environment, always: bool) -> Option<String> {
+    let elapsed = env.elapsed_time?;
+    let threshold = env::var("PS1_NOTIFY_THRESHOLD")
+        .ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .map(|seconds| seconds.saturating_mul(1_000_000));
+    if !should_notify(elapsed, threshold, || if always { None } else { focused() }) {
+        return None;
+    }
+
+    let (title, status) = match env.ret_code {
+        Some(0) => ("Command finished", "exit 0".to_owned()),
+        Some(code) => ("Command failed", format!("exit {code}")),
+        None => ("Command finished", "exit status unknown".to_owned()),
+    };
+    let took = time::microseconds_to_string(elapsed).unwrap_or_default();
+    let body = format!("{status} after {took} in {}@{}", env.user, env.host);
+    Some(
+        Protocol::configured()
+            .into_iter()
+            .map(|protocol| body.as_notification(protocol, title).to_string())
+            .collect(),
+    )
+}
+
+/// Asks the terminal whether it is focused. Terminals answer `CSI I` or `CSI O` right after
+/// focus reporting is enabled, or never if they do not support it. Not asked if something typed
+/// ahead is waiting, since reading the answer would take it away from the shell
+#[must_use]
+pub fn focused() -> Option<bool> {
+    let tty = open("/dev/tty", OFlags::RDWR | OFlags::NOCTTY, Mode::empty()).ok()?;
+    if ioctl_fionread(&tty).ok()? != 0 {
+        return None;
+    }
+    let saved = tcgetattr(&tty).ok()?;
+    let mut raw = saved.clone();
+    raw.make_raw();
+    tcsetattr(&tty, OptionalActions::Now, &raw).ok()?;
+
+    let answer = (|| {
+        write(&tty, b"\x1b[?1004h").ok()?;
+        let mut reply = vec![];
+        let mut buf = [0_u8; 16];
+        loop {
+            if poll(&mut [PollFd::new(&tty, PollFlags::IN)], 100_i32).ok()? == 0 {
+                return None;
+            }
+            let len = read(&tty, &mut buf).ok()?;
+            reply.extend_from_slice(buf.get(..len)?);
+            if reply.windows(3).any(|w| w == b"\x1b[I") {
+                return Some(true);
+            }
+            if reply.windows(3).any(|w| w == b"\x1b[O") {
+                return Some(false);
+            }
+            if len == 0 || reply.len() > 64 {
+                return None;
+            }
+        }
+    })();
+
+    write(&tty, b"\x1b[?1004l").unwrap_or_default();
+    tcsetattr(&tty, OptionalActions::Now, &saved).unwrap_or_default();
+    answer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Protocol, should_notify};
+
+    #[test]
+    fn protocols() {
+        for (name, expected) in [
+            ("osc9", Some(Protocol::Osc9)),
+            (" 777", Some(Protocol::Osc777)),
+            ("bell", Some(Protocol::Bell)),
+            ("osc99", None),
+        ] {
+            assert_eq!(Protocol::from_name(name), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn thresholds() {
+        for (elapsed, threshold, focus, expected) in [
+            (5_000_000, None, None, false),
+            (5_000_000, Some(10_000_000), None, false),
+            (10_000_000, Some(10_000_000), None, true),
+            (60_000_000, Some(10_000_000), Some(false), true),
+            (60_000_000, Some(10_000_000), Some(true), false),
+        ] {
+            assert_eq!(
+                should_notify(elapsed, threshold, || focus),
+                expected,
+                "{elapsed} over {threshold:?} with focus {focus:?}"
+            );
+        }
+    }
+}
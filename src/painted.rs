@@ -0,0 +1,142 @@
+//! Rows painted above the bottom line
+//!
+//! `run` records the width of every row it paints, and `transient` reads the record back to know
+//! how many rows to collapse, instead of laying the top line out once again. Records are named
+//! after the prompt's control pipe, and `run` removes its record once the prompt is gone
+
+use crate::file;
+use rustix::{fd::AsFd, fs::fstat, process::getuid};
+use std::{
+    env,
+    fmt::{self, Display, Formatter},
+    fs::{self, DirBuilder},
+    io::ErrorKind,
+    os::unix::fs::{DirBuilderExt as _, MetadataExt as _},
+    path::PathBuf,
+};
+
+/// Widths of the painted rows, top to bottom, in columns
+#[derive(Debug, PartialEq, Eq)]
+pub struct Painted(Vec<usize>);
+
+impl Painted {
+    #[must_use]
+    pub fn new(widths: Vec<usize>) -> Self {
+        Painted(widths)
+    }
+
+    /// Terminal rows taken at the given width. Terminals which rewrap lines on resize split rows
+    /// wider than that, others cut them
+    #[must_use]
+    pub fn rows(&self, columns: usize, rewrap: bool) -> usize {
+        if !rewrap {
+            return self.0.len();
+        }
+        self.0
+            .iter()
+            .map(|width| width.div_ceil(columns.max(1)).max(1))
+            .sum()
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let widths = text
+            .split_whitespace()
+            .map(|width| width.parse().ok())
+            .collect::<Option<Vec<_>>>()?;
+        (!widths.is_empty()).then_some(Painted(widths))
+    }
+
+    /// Records the rows for the prompt with this control pipe
+    pub fn save(&self, control: impl AsFd) {
+        if let Some(path) = record(control) {
+            file::store(&path, &format!("{self}\n"));
+        }
+    }
+
+    /// Rows recorded for the prompt with this control pipe
+    #[must_use]
+    pub fn load(control: impl AsFd) -> Option<Self> {
+        Painted::parse(&fs::read_to_string(record(control)?).ok()?)
+    }
+
+    /// Forgets the rows of the prompt with this control pipe
+    pub fn remove(control: impl AsFd) {
+        if let Some(path) = record(control) {
+            fs::remove_file(path).unwrap_or_default();
+        }
+    }
+}
+
+impl Display for Painted {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let widths = self.0.iter().map(usize::to_string).collect::<Vec<_>>();
+        write!(f, "{}", widths.join(" "))
+    }
+}
+
+/// Whether the terminal rewraps lines on resize. Most do, xterm does not
+#[must_use]
+pub fn rewraps() -> bool {
+    env::var_os("XTERM_VERSION").is_none()
+}
+
+/// Record file, named after the inode of the control pipe, which is unique while the pipe is open
+fn record(control: impl AsFd) -> Option<PathBuf> {
+    let inode = fstat(control).ok()?.st_ino;
+    Some(runtime_dir()?.join(format!("painted-{inode}")))
+}
+
+/// `$XDG_RUNTIME_DIR/statusline`, or a private directory in `/tmp` if there is no runtime one
+fn runtime_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+    {
+        return Some(dir.join("statusline"));
+    }
+    let uid = getuid().as_raw();
+    let dir = env::temp_dir().join(format!("statusline-{uid}"));
+    match DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(()) => {}
+        Err(error) if error.kind() == ErrorKind::AlreadyExists => {}
+        Err(_) => return None,
+    }
+    // Anyone could have created it before
+    let meta = fs::symlink_metadata(&dir).ok()?;
+    (meta.is_dir() && meta.uid() == uid && meta.mode() & 0o777 == 0o700).then_some(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Painted;
+
+    #[test]
+    fn rows() {
+        let painted = Painted::new(vec![119, 45]);
+        for (columns, rewrap, expected) in [
+            (120, true, 2),
+            (200, true, 2),
+            (119, true, 2),
+            (60, true, 3),
+            (40, true, 5),
+            (40, false, 2),
+            (0, true, 164),
+        ] {
+            assert_eq!(
+                painted.rows(columns, rewrap),
+                expected,
+                "{columns} columns, rewrap {rewrap}"
+            );
+        }
+        assert_eq!(Painted::new(vec![0]).rows(80, true), 1, "empty row");
+    }
+
+    #[test]
+    fn records() {
+        let painted = Painted::new(vec![119, 45]);
+        assert_eq!(painted.to_string(), "119 45", "format");
+        assert_eq!(Painted::parse("119 45\n"), Some(painted), "round trip");
+        assert_eq!(Painted::parse(""), None, "empty");
+        assert_eq!(Painted::parse("119 x"), None, "broken");
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    cell::RefCell,
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Filesystem root for chassis and virtualization detection
+///
+/// Detectors read `/sys`, `/proc`, `/run` and `/etc` relative to this root, so they can be pointed
+/// to a copy of another machine's files. Every decision is noted, to be able to tell why something
+/// was detected. CPUID is only queried when the root is `/`, because it describes the host
+///
+/// ```
+/// use statusline::{Chassis, Probe};
+///
+/// let probe = Probe::new("/nonexistent");
+/// assert!(matches!(Chassis::detect(&probe), Chassis::Unknown));
+/// ```
+pub struct Probe {
+    root: PathBuf,
+    notes: RefCell<Vec<String>>,
+}
+
+impl Default for Probe {
+    fn default() -> Self {
+        Probe::new("/")
+    }
+}
+
+impl Probe {
+    /// Creates detection context with a given filesystem root
+    #[must_use]
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Probe {
+            root: root.into(),
+            notes: RefCell::new(vec![]),
+        }
+    }
+
+    /// Resolves an absolute path against the root
+    #[must_use]
+    pub fn path<P: AsRef<Path> + ?Sized>(&self, path: &P) -> PathBuf {
+        let path = path.as_ref();
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    /// Whether the root is the real one, so machine-wide facts like CPUID apply to it
+    #[must_use]
+    pub fn is_host(&self) -> bool {
+        self.root == Path::new("/")
+    }
+
+    /// Environment variable of the probed machine. Only the host's own environment is known, so
+    /// it is always unset under another root
+    #[must_use]
+    pub fn var(&self, name: &str) -> Option<String> {
+        self.is_host().then(|| env::var(name).ok()).flatten()
+    }
+
+    /// Records the reason of a decision
+    pub fn note<S: Into<String>>(&self, why: S) {
+        self.notes.borrow_mut().push(why.into());
+    }
+
+    /// Reasons recorded so far, in order
+    #[must_use]
+    pub fn notes(&self) -> Vec<String> {
+        self.notes.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+pub mod fixture {
+    use super::Probe;
+    use std::{
+        env, fs,
+        path::PathBuf,
+        process,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    /// Temporary directory tree for detection tests, removed on drop
+    pub struct Fixture(PathBuf);
+
+    impl Fixture {
+        /// Creates files with given contents. Paths ending in `/` are created as directories
+        pub fn new(files: &[(&str, &str)]) -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let root = env::temp_dir().join(format!(
+                "statusline-fixture-{}-{}",
+                process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            for (path, contents) in files {
+                let path = root.join(path.trim_start_matches('/'));
+                if path.to_string_lossy().ends_with('/') {
+                    fs::create_dir_all(&path).expect("fixture dir should be creatable");
+                } else {
+                    fs::create_dir_all(path.parent().expect("fixture file has a parent"))
+                        .expect("fixture dir should be creatable");
+                    fs::write(&path, contents).expect("fixture file should be writable");
+                }
+            }
+            fs::create_dir_all(&root).expect("fixture root should be creatable");
+            Fixture(root)
+        }
+
+        pub fn probe(&self) -> Probe {
+            Probe::new(&self.0)
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            fs::remove_dir_all(&self.0).unwrap_or_default();
+        }
+    }
+}
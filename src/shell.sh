@@ -1,12 +1,26 @@
 exec {PS1_FD}<> <(true)
+export PS1_FD
 
 VIRTUAL_ENV_DISABLE_PROMPT=1
 PS1_START="${EPOCHREALTIME/[.,]/}"
 
-trap 'echo >&$PS1_FD' DEBUG
-PS0='${PS1_START:0:$((PS1_START=${EPOCHREALTIME/[.,]/}, 0))}'
+trap '
+    if (( PS1_SUBMITTED )); then
+        PS1_SUBMITTED=0
+        if [[ -n "$PS1_TRANSIENT" ]] && (( HISTCMD == PS1_HISTCMD )); then
+            PS1_JOBS=($(jobs -p))
+            "<exec>" transient --return-code "$PS1_STATUS" --pipe-status "$PS1_PIPESTATUS" --jobs-count "${#PS1_JOBS[@]}" --elapsed-time "$PS1_ELAPSED" --command "$(HISTTIMEFORMAT= history 1)" --control-fd 3 3<&$PS1_FD
+        fi
+        echo >&$PS1_FD
+    fi
+' DEBUG
+PS0='${PS1_START:0:$((PS1_START=${EPOCHREALTIME/[.,]/}, PS1_SUBMITTED=1, 0))}\e]133;C\a'
 PROMPT_COMMAND='
+    PS1_STATUS="$?" PS1_PIPESTATUS="${PIPESTATUS[*]}" PS1_HISTCMD="$HISTCMD"
+    echo >&$PS1_FD
+    exec {PS1_FD}<&- {PS1_FD}<> <(:)
     if [[ -n "$PS1_START" ]]; then
+        printf "\e]133;D;%s\a" "$PS1_STATUS"
         PS1_END="${EPOCHREALTIME/[.,]/}"
         PS1_ELAPSED="$((PS1_END - PS1_START))"
         PS1_START=
@@ -15,7 +29,7 @@ PROMPT_COMMAND='
     fi
     jobs -n
 '
-PS1='$("<exec>" run --return-code "$?" --jobs-count "\j" --elapsed-time "$PS1_ELAPSED" --control-fd 3 3<&$PS1_FD &)'
+PS1='$("<exec>" run --return-code "$?" --pipe-status "$PS1_PIPESTATUS" --jobs-count "\j" --elapsed-time "$PS1_ELAPSED" ${PS1_NOTIFY_ALWAYS:+--notify-always} --shell-pid "$$" --control-fd 3 3<&$PS1_FD {PS1_FD}<&- &)'
 
 alias ssh='WORKGROUP_CHAIN="$("<exec>" chain)" ssh -o "SendEnv=WORKGROUP_CHAIN"'
 
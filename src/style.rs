@@ -1,3 +1,6 @@
+use crate::{
+    Color, ColorDepth, ColorRules, Hyperlinks, hyperlink::percent_encode, notify::Protocol,
+};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
 const INVISIBLE_START: &str = "\x01";
@@ -6,37 +9,32 @@ const ESC: &str = "\x1b";
 const CSI: &str = "\x1b[";
 const RESET: &str = "\x1b[0m";
 const BEL: &str = "\x07";
-const HSV_COLOR_TABLE: [(u8, u8, u8); 24] = [
-    (255, 0, 0),
-    (255, 85, 0),
-    (255, 128, 0),
-    (255, 170, 0),
-    (255, 213, 0),
-    (255, 255, 0),
-    (213, 255, 0),
-    (170, 255, 0),
-    (128, 255, 0),
-    (0, 255, 85),
-    (0, 255, 128),
-    (0, 255, 170),
-    (0, 255, 213),
-    (0, 213, 255),
-    (0, 128, 255),
-    (0, 85, 255),
-    (128, 0, 255),
-    (170, 0, 255),
-    (213, 0, 255),
-    (255, 0, 255),
-    (255, 0, 212),
-    (255, 0, 170),
-    (255, 0, 128),
-    (255, 0, 85),
-];
+const ST: &str = "\x1b\\";
+
+/// OSC 133 semantic prompt mark, which lets terminals jump between prompts and select command
+/// output
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Mark {
+    /// `A`, prompt is about to be printed
+    PromptStart,
+    /// `B`, prompt is printed and the command is typed after it
+    CommandStart,
+    /// `C`, command is running and printing its output
+    CommandExecuted,
+    /// `D`, command has finished, with its exit status
+    CommandFinished(Option<u8>),
+}
 
 enum StyleKind {
     Title,
+    Notification(Protocol, String),
+    Mark(Mark),
+    WorkingDirectory(String),
+    Hyperlink(String),
     Bold,
     Italic,
+    Inverse,
     Color8(usize),
     TrueColor(u8, u8, u8),
     ResetEnd,
@@ -46,8 +44,12 @@ enum StyleKind {
     Rounded,
     CursorHorizontalAbsolute(usize),
     CursorPreviousLine(i32),
+    CursorUp(i32),
     CursorSaveRestore,
     ClearLine,
+    DeleteLine(i32),
+    InsertLine(i32),
+    ScrollUp(usize),
     NewlineJoin(String),
 }
 
@@ -84,12 +86,50 @@ impl<T: Display + ?Sized> Display for Styled<'_, T> {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         match &self.style {
             StyleKind::Title => write!(f, "{ESC}]0;{}{BEL}", self.value),
+            StyleKind::Notification(Protocol::Osc9, _) => write!(f, "{ESC}]9;{}{BEL}", self.value),
+            StyleKind::Notification(Protocol::Osc777, title) => {
+                write!(f, "{ESC}]777;notify;{title};{}{BEL}", self.value)
+            }
+            StyleKind::Notification(Protocol::Bell, _) => write!(f, "{BEL}"),
+            StyleKind::Mark(mark) => {
+                let mark = match mark {
+                    Mark::PromptStart => "A".to_owned(),
+                    Mark::CommandStart => "B".to_owned(),
+                    Mark::CommandExecuted => "C".to_owned(),
+                    Mark::CommandFinished(Some(code)) => format!("D;{code}"),
+                    Mark::CommandFinished(None) => "D".to_owned(),
+                };
+                write!(f, "{ESC}]133;{mark}{BEL}{}", self.value)
+            }
+            StyleKind::WorkingDirectory(host) => write!(
+                f,
+                "{ESC}]7;file://{host}{}{BEL}",
+                percent_encode(&self.value.to_string())
+            ),
+            StyleKind::Hyperlink(_) if Hyperlinks::current() == Hyperlinks::Disabled => {
+                write!(f, "{}", self.value)
+            }
+            StyleKind::Hyperlink(url) => write!(
+                f,
+                "{INVISIBLE_START}{ESC}]8;;{url}{ST}{INVISIBLE_END}{}\
+                 {INVISIBLE_START}{ESC}]8;;{ST}{INVISIBLE_END}",
+                self.value
+            ),
             StyleKind::Bold => write!(f, "{CSI}1m{}", self.value),
             StyleKind::Italic => write!(f, "{CSI}3m{}", self.value),
-            StyleKind::Color8(index) => write!(f, "{CSI}{}m{}", index + 31, self.value),
-            StyleKind::TrueColor(r, g, b) => {
-                write!(f, "{CSI}38;2;{r};{g};{b}m{}", self.value)
+            StyleKind::Inverse => write!(f, "{CSI}7m{}", self.value),
+            StyleKind::Color8(_) | StyleKind::TrueColor(..)
+                if ColorDepth::current() == ColorDepth::Monochrome =>
+            {
+                write!(f, "{}", self.value)
             }
+            StyleKind::Color8(index) => write!(f, "{CSI}{}m{}", index + 31, self.value),
+            StyleKind::TrueColor(r, g, b) => write!(
+                f,
+                "{CSI}{}m{}",
+                ColorDepth::current().foreground(*r, *g, *b),
+                self.value
+            ),
             StyleKind::ResetEnd => write!(f, "{}{RESET}", self.value),
             StyleKind::Invisible => write!(f, "{INVISIBLE_START}{}{INVISIBLE_END}", self.value),
             StyleKind::Visible => write!(f, "{INVISIBLE_END}{}{INVISIBLE_START}", self.value),
@@ -97,8 +137,14 @@ impl<T: Display + ?Sized> Display for Styled<'_, T> {
             StyleKind::Rounded => write!(f, "({})", self.value),
             StyleKind::CursorHorizontalAbsolute(n) => write!(f, "{CSI}{n}G{}", self.value),
             StyleKind::CursorPreviousLine(n) => write!(f, "{CSI}{n}A{CSI}G{}", self.value),
+            StyleKind::CursorUp(n) => write!(f, "{CSI}{n}A{}", self.value),
             StyleKind::CursorSaveRestore => write!(f, "{CSI}s{}{CSI}u", self.value),
             StyleKind::ClearLine => write!(f, "{CSI}0K{}", self.value),
+            StyleKind::DeleteLine(n) => write!(f, "{CSI}{n}M{}", self.value),
+            StyleKind::InsertLine(n) => write!(f, "{CSI}{n}L{}", self.value),
+            StyleKind::ScrollUp(n) => {
+                write!(f, "{CSI}999B{}{}", format!("{ESC}D").repeat(*n), self.value)
+            }
             StyleKind::NewlineJoin(s) => write!(f, "{}\n{s}", self.value),
         }
     }
@@ -131,6 +177,73 @@ pub trait Style: Display {
         }
     }
 
+    /// Format as a desktop notification. Title is not shown with OSC 9, and nothing but the bell
+    /// itself is sent with BEL
+    ///
+    /// ```
+    /// use statusline::{Style, notify::Protocol};
+    /// assert_eq!("\x1b]9;Done\x07", "Done".as_notification(Protocol::Osc9, "make").to_string());
+    /// assert_eq!(
+    ///     "\x1b]777;notify;make;Done\x07",
+    ///     "Done".as_notification(Protocol::Osc777, "make").to_string()
+    /// );
+    /// assert_eq!("\x07", "Done".as_notification(Protocol::Bell, "make").to_string());
+    /// ```
+    fn as_notification(&self, protocol: Protocol, title: &str) -> Styled<Self> {
+        Styled {
+            style: StyleKind::Notification(protocol, title.replace(';', ",")),
+            value: self,
+        }
+    }
+
+    /// Prepend semantic prompt mark
+    ///
+    /// ```
+    /// use statusline::{Mark, Style};
+    /// assert_eq!("\x1b]133;A\x07$ ", "$ ".with_mark(Mark::PromptStart).to_string());
+    /// assert_eq!("\x1b]133;D;1\x07", "".with_mark(Mark::CommandFinished(Some(1))).to_string());
+    /// ```
+    fn with_mark(&self, mark: Mark) -> Styled<Self> {
+        Styled {
+            style: StyleKind::Mark(mark),
+            value: self,
+        }
+    }
+
+    /// Format as OSC 7 working directory report, so terminals open new tabs in the same
+    /// directory. The path is percent-encoded
+    ///
+    /// ```
+    /// use statusline::Style;
+    /// assert_eq!(
+    ///     "\x1b]7;file://reimu/home/yuki/my%20notes\x07",
+    ///     "/home/yuki/my notes".as_working_directory("reimu").to_string()
+    /// );
+    /// ```
+    fn as_working_directory(&self, host: &str) -> Styled<Self> {
+        Styled {
+            style: StyleKind::WorkingDirectory(host.to_owned()),
+            value: self,
+        }
+    }
+
+    /// Make a hyperlink with OSC 8, unless [`Hyperlinks`] are disabled. Escape sequences are
+    /// readline invisible, so this is used on visible text
+    ///
+    /// ```
+    /// use statusline::Style;
+    /// assert_eq!(
+    ///     "\x01\x1b]8;;https://codeberg.org\x1b\\\x02forge\x01\x1b]8;;\x1b\\\x02",
+    ///     "forge".hyperlink("https://codeberg.org").to_string()
+    /// );
+    /// ```
+    fn hyperlink(&self, url: &str) -> Styled<Self> {
+        Styled {
+            style: StyleKind::Hyperlink(url.to_owned()),
+            value: self,
+        }
+    }
+
     /// Prepend bold style. Colors from 16-color palette may shift a bit
     ///
     /// ```
@@ -157,6 +270,20 @@ pub trait Style: Display {
         }
     }
 
+    /// Prepend inverse style, which swaps foreground and background colors. It is visible even
+    /// without colors
+    ///
+    /// ```
+    /// use statusline::Style;
+    /// assert_eq!("\x1b[7mInverse text", "Inverse text".inverse().to_string());
+    /// ```
+    fn inverse(&self) -> Styled<Self> {
+        Styled {
+            style: StyleKind::Inverse,
+            value: self,
+        }
+    }
+
     /// Use colors from 16-color palette, dark version (0 for CSI 31 thru 6 for CSI 37, CSI 30 is black which
     /// is useless)
     fn low_color(&self, index: usize) -> Styled<Self> {
@@ -166,11 +293,8 @@ pub trait Style: Display {
         }
     }
 
-    /// Use true color. Note that some terminals lack true color support and will approximate
-    /// the result with colors they do support. This may lead to text being completely unreadable.
-    ///
-    /// However, since most GUI terminal emulators in linux do support true color display no worry
-    /// is usually needed. Just use it as-is
+    /// Use true color. Terminals without true color support get the nearest color they have,
+    /// according to installed [`ColorDepth`]
     fn true_color(&self, red: u8, green: u8, blue: u8) -> Styled<Self> {
         Styled {
             style: StyleKind::TrueColor(red, green, blue),
@@ -261,6 +385,14 @@ pub trait Style: Display {
         }
     }
 
+    /// Move cursor `count` lines up, staying in the same column
+    fn cursor_up(&self, count: i32) -> Styled<Self> {
+        Styled {
+            style: StyleKind::CursorUp(count),
+            value: self,
+        }
+    }
+
     /// Wrap into cursor saver --- for example for outputting PS1 above the PS1 "line"
     fn save_restore(&self) -> Styled<Self> {
         Styled {
@@ -277,6 +409,32 @@ pub trait Style: Display {
         }
     }
 
+    /// Prepends remover of `count` lines, which pulls the lines below them up. Zero counts as one
+    fn delete_line(&self, count: i32) -> Styled<Self> {
+        Styled {
+            style: StyleKind::DeleteLine(count),
+            value: self,
+        }
+    }
+
+    /// Prepends inserter of `count` empty lines, which pushes the lines below them down. Zero
+    /// counts as one
+    fn insert_line(&self, count: i32) -> Styled<Self> {
+        Styled {
+            style: StyleKind::InsertLine(count),
+            value: self,
+        }
+    }
+
+    /// Prepends scroll of the whole screen `count` lines up, which leaves the cursor on the last
+    /// line
+    fn scroll_up(&self, count: usize) -> Styled<Self> {
+        Styled {
+            style: StyleKind::ScrollUp(count),
+            value: self,
+        }
+    }
+
     /// Join current line with fixed one with newline
     fn join_lf(&self, s: String) -> Styled<Self> {
         Styled {
@@ -344,30 +502,25 @@ pub trait Style: Display {
     ///
     /// Colors `self` with a "random" color associated with given string `with`.
     ///
-    /// |`with` value|Resulting color     |
-    /// |------------|--------------------|
-    /// |`="root"`   | Red                |
-    /// |other       | Some non-red color |
+    /// |`with` value              |Resulting color     |
+    /// |--------------------------|--------------------|
+    /// |pinned by [`ColorRules`]  | Pinned color       |
+    /// |`="root"`                 | Red                |
+    /// |other                     | Some non-red color |
     ///
     /// There are 24 different colors
     fn colorize_with(&self, with: &str) -> Styled<Self> {
-        if with == "root" {
-            self.red()
-        } else {
-            let idx = polyhash(with, 23, 179, with.len()) + 1;
-            let (r, g, b) = HSV_COLOR_TABLE[idx];
-            self.true_color(r, g, b)
+        self.with_color(ColorRules::current().color_for(with))
+    }
+
+    /// Use given color, either from 16-color palette or true one
+    fn with_color(&self, color: Color) -> Styled<Self> {
+        match color {
+            Color::Low(index) => self.low_color(index),
+            Color::True(r, g, b) => self.true_color(r, g, b),
         }
     }
 }
 
 /// All types which can be displayed can be styled too
 impl<T: Display + ?Sized> Style for T {}
-
-fn polyhash(s: &str, m: usize, p: usize, h_init: usize) -> usize {
-    let mut h = h_init % m;
-    for by in s.bytes() {
-        h = (h * p + by as usize) % m;
-    }
-    h
-}
@@ -0,0 +1,418 @@
+//! Runnable targets of the project, for `statusline targets` and the extended line
+//!
+//! Makefile rules, justfile recipes, `package.json` scripts and cargo aliases are read without
+//! running any of the tools. Parsed targets are cached by file modification time
+
+use crate::{block::build_info, file, ini, json::Json};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Cached files, older entries are dropped first
+const CACHE_ENTRIES: usize = 128;
+
+/// Targets defined in a single file
+pub struct Source {
+    /// Command to run targets with
+    pub tool: &'static str,
+    /// File the targets are defined in
+    pub path: PathBuf,
+    /// Target names, in file order
+    pub targets: Vec<String>,
+}
+
+/// Finds all target definitions for the working directory, searching parents where the tool does
+#[must_use]
+pub fn find(workdir: &Path) -> Vec<Source> {
+    find_cached(workdir, cache_path().as_deref())
+}
+
+fn find_cached(workdir: &Path, cache: Option<&Path>) -> Vec<Source> {
+    let mut sources = vec![];
+    let mut add = |tool, path: Option<PathBuf>, parse: fn(&str) -> Vec<String>| {
+        if let Some(path) = path
+            && let Some(targets) = cached(cache, &path, parse)
+        {
+            sources.push(Source {
+                tool,
+                path,
+                targets,
+            });
+        }
+    };
+
+    let here = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| workdir.join(name))
+            .find(file::points_to_file)
+    };
+    let upward = |names: &[&str]| {
+        workdir.ancestors().find_map(|dir| {
+            names
+                .iter()
+                .map(|name| dir.join(name))
+                .find(file::points_to_file)
+        })
+    };
+
+    add(
+        "make",
+        here(&["GNUmakefile", "makefile", "Makefile"]),
+        makefile,
+    );
+    add(
+        "just",
+        upward(&["justfile", "Justfile", ".justfile"]),
+        justfile,
+    );
+    let package = upward(&["package.json"]);
+    let node = package.as_deref().map_or("npm", build_info::node_tool);
+    add(node, package, scripts);
+    add(
+        "cargo",
+        upward(&[".cargo/config.toml", ".cargo/config"]),
+        cargo_aliases,
+    );
+    sources
+}
+
+/// Top-level rules. Pattern rules, special targets like `.PHONY` and variable targets are skipped
+fn makefile(text: &str) -> Vec<String> {
+    let mut targets = vec![];
+    let mut continued = false;
+    let mut in_define = false;
+    for line in text.lines() {
+        let was_continued = continued;
+        continued = line.ends_with('\\');
+        let word = line.split_whitespace().next().unwrap_or_default();
+        if in_define {
+            in_define = word != "endef";
+            continue;
+        }
+        if word == "define" {
+            in_define = true;
+            continue;
+        }
+        if was_continued || line.starts_with(['\t', ' ', '#']) {
+            continue;
+        }
+        let Some((names, rest)) = line.split_once(':') else {
+            continue;
+        };
+        // `VAR := value`, `VAR ::= value` and `VAR = a:b` are assignments
+        if rest.starts_with('=') || rest.starts_with(":=") || names.contains('=') {
+            continue;
+        }
+        for name in names.split_whitespace() {
+            if !name.starts_with('.')
+                && !name.contains(['%', '$', '(', '/'])
+                && !targets.iter().any(|target| target == name)
+            {
+                targets.push(name.to_owned());
+            }
+        }
+    }
+    targets
+}
+
+/// Public recipes. Recipes starting with `_` or marked `[private]` are skipped
+fn justfile(text: &str) -> Vec<String> {
+    const KEYWORDS: [&str; 6] = ["alias", "export", "import", "mod", "set", "unexport"];
+    let mut targets = vec![];
+    let mut private = false;
+    for line in text.lines() {
+        if line.starts_with([' ', '\t', '#']) || line.trim().is_empty() {
+            continue;
+        }
+        if let Some(attributes) = line.strip_prefix('[') {
+            private |= attributes.contains("private");
+            continue;
+        }
+        let was_private = private;
+        private = false;
+        let recipe = line.trim_start_matches('@');
+        let name_end = recipe
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '-')
+            .unwrap_or(recipe.len());
+        let (name, rest) = recipe.split_at(name_end);
+        let Some(colon) = rest.find(':') else {
+            continue;
+        };
+        if name.is_empty()
+            || was_private
+            || name.starts_with('_')
+            || KEYWORDS.contains(&name)
+            || rest
+                .get(colon..)
+                .is_some_and(|after| after.starts_with(":="))
+            || rest
+                .get(..colon)
+                .is_some_and(|params| params.contains(":="))
+        {
+            continue;
+        }
+        targets.push(name.to_owned());
+    }
+    targets
+}
+
+/// `scripts` of `package.json`. Lifecycle hooks like `prebuild` are skipped when their script is
+/// defined too
+fn scripts(text: &str) -> Vec<String> {
+    let package = Json::parse(text);
+    let Some(scripts) = package
+        .as_ref()
+        .and_then(|package| package.get("scripts")?.as_object())
+    else {
+        return vec![];
+    };
+    let names = scripts
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    names
+        .iter()
+        .filter(|name| {
+            !["pre", "post"].iter().any(|hook| {
+                name.strip_prefix(hook)
+                    .is_some_and(|script| names.contains(&script))
+            })
+        })
+        .map(|name| (*name).to_owned())
+        .collect()
+}
+
+/// `[alias]` entries of cargo config
+fn cargo_aliases(text: &str) -> Vec<String> {
+    ini::entries(text, "alias")
+        .map(|(name, _)| name.trim_matches('"').to_owned())
+        .collect()
+}
+
+/// `$XDG_CACHE_HOME/statusline/targets`
+fn cache_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| Some(PathBuf::from(env::var_os("HOME")?).join(".cache")))?;
+    Some(dir.join("statusline/targets"))
+}
+
+/// Modification time as cache key, with nanoseconds since editors may save twice a second
+fn mtime(path: &Path) -> Option<String> {
+    let time = fs::metadata(path).ok()?.modified().ok()?;
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    Some(format!("{}.{:09}", since.as_secs(), since.subsec_nanos()))
+}
+
+/// Parses the file, or takes its targets from cache if it was not modified since. Cache lines are
+/// `path TAB mtime TAB target TAB target...`
+fn cached(
+    cache: Option<&Path>,
+    path: &Path,
+    parse: fn(&str) -> Vec<String>,
+) -> Option<Vec<String>> {
+    let key = path.to_str().filter(|key| !key.contains(['\t', '\n']))?;
+    let mtime = mtime(path)?;
+    let lines = cache
+        .and_then(|cache| fs::read_to_string(cache).ok())
+        .unwrap_or_default();
+    let hit = lines.lines().find_map(|line| {
+        let mut fields = line.split('\t');
+        (fields.next()? == key && fields.next()? == mtime)
+            .then(|| fields.map(str::to_owned).collect())
+    });
+    if hit.is_some() {
+        return hit;
+    }
+
+    let targets = parse(&file::read_small(path)?);
+    if let Some(cache) = cache {
+        let mut kept = lines
+            .lines()
+            .filter(|line| line.split('\t').next() != Some(key))
+            .collect::<Vec<_>>();
+        kept.drain(..kept.len().saturating_sub(CACHE_ENTRIES - 1));
+        let entry = [key, &mtime]
+            .into_iter()
+            .chain(targets.iter().map(String::as_str))
+            .filter(|field| !field.contains(['\t', '\n']))
+            .collect::<Vec<_>>()
+            .join("\t");
+        kept.push(&entry);
+        file::store(cache, &(kept.join("\n") + "\n"));
+    }
+    Some(targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cached, cargo_aliases, find_cached, justfile, makefile, scripts};
+    use crate::probe::fixture::Fixture;
+    use std::{
+        fs::{self, File},
+        time::Duration,
+    };
+
+    #[test]
+    fn makefile_rules() {
+        let text = "\
+CC := gcc
+FLAGS = -o:x
+.PHONY: all clean
+all: build
+build test: $(OBJS)
+\t@echo not: a target
+%.o: %.c
+$(OUT): main.o
+define RECIPE
+inner: rule
+endef
+SRCS = main.c \\
+lib:x.c
+clean::
+lib/x.a: y
+install: FLAGS += -s
+";
+        assert_eq!(
+            makefile(text),
+            ["all", "build", "test", "clean", "install"],
+            "rules"
+        );
+    }
+
+    #[test]
+    fn justfile_recipes() {
+        let text = "\
+set shell := [\"bash\", \"-c\"]
+alias b := build
+version := `git describe`
+
+default:
+    just --list
+
+# builds it
+build target=\"release\": (check target)
+    cargo build
+
+@test *args:
+    cargo test {{args}}
+
+_helper:
+    true
+
+[private]
+hidden:
+    true
+
+[group('ci')]
+deploy env:
+    ./deploy {{env}}
+";
+        assert_eq!(
+            justfile(text),
+            ["default", "build", "test", "deploy"],
+            "recipes"
+        );
+    }
+
+    #[test]
+    fn package_scripts() {
+        let text = r#"{"name": "x", "scripts": {"prebuild": "a", "build": "b", "postinstall": "c", "test:unit": "d"}}"#;
+        assert_eq!(
+            scripts(text),
+            ["build", "postinstall", "test:unit"],
+            "scripts"
+        );
+        assert_eq!(scripts("{}"), Vec::<String>::new(), "no scripts");
+        assert_eq!(scripts("not json"), Vec::<String>::new(), "broken");
+        let deep = format!(
+            "{{\"scripts\": {}{}}}",
+            "[".repeat(200_000),
+            "]".repeat(200_000)
+        );
+        assert_eq!(scripts(&deep), Vec::<String>::new(), "too deep");
+    }
+
+    #[test]
+    fn cargo_alias() {
+        let text =
+            "[build]\njobs = 4\n[alias]\nb = \"build\"\nxtask = \"run --package xtask --\"\n";
+        assert_eq!(cargo_aliases(text), ["b", "xtask"], "aliases");
+    }
+
+    #[test]
+    fn sources() {
+        let fixture = Fixture::new(&[
+            ("/p/justfile", "build:\n\ttrue\n"),
+            ("/p/package.json", r#"{"scripts": {"dev": "vite"}}"#),
+            ("/p/yarn.lock", ""),
+            (
+                "/p/.cargo/config.toml",
+                "[alias]\nxtask = \"run -p xtask\"\n",
+            ),
+            ("/p/sub/Makefile", "all:\n"),
+        ]);
+        let found = find_cached(&fixture.probe().path("/p/sub"), None)
+            .into_iter()
+            .map(|source| (source.tool, source.targets))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("make", vec!["all".to_owned()]),
+                ("just", vec!["build".to_owned()]),
+                ("yarn", vec!["dev".to_owned()]),
+                ("cargo", vec!["xtask".to_owned()]),
+            ],
+            "sources"
+        );
+    }
+
+    #[test]
+    fn hostile_files() {
+        let deep = format!("{}{}", "{\"a\":".repeat(200_000), "}".repeat(200_000));
+        let huge = format!("all:\n{}", "#".repeat(2 << 20));
+        let fixture = Fixture::new(&[("/p/package.json", &deep), ("/p/Makefile", &huge)]);
+        assert!(
+            find_cached(&fixture.probe().path("/p"), None).is_empty(),
+            "no targets from hostile files"
+        );
+    }
+
+    #[test]
+    fn cache() {
+        let fixture = Fixture::new(&[("/Makefile", "all:\n"), ("/cache/", "")]);
+        let probe = fixture.probe();
+        let (makefile_path, cache) = (probe.path("/Makefile"), probe.path("/cache/targets"));
+
+        let parsed = cached(Some(&cache), &makefile_path, makefile);
+        assert_eq!(parsed, Some(vec!["all".to_owned()]), "parsed");
+        let stored = fs::read_to_string(&cache).expect("cache should be written");
+
+        let hit = cached(Some(&cache), &makefile_path, |_| vec!["stale".to_owned()]);
+        assert_eq!(hit, Some(vec!["all".to_owned()]), "same mtime hits cache");
+
+        let written = fs::metadata(&makefile_path)
+            .and_then(|meta| meta.modified())
+            .expect("makefile should have mtime");
+        fs::write(&makefile_path, "all:\nother:\n").expect("makefile should be writable");
+        File::options()
+            .write(true)
+            .open(&makefile_path)
+            .and_then(|file| file.set_modified(written + Duration::from_secs(1)))
+            .expect("makefile mtime should be settable");
+        let fresh = cached(Some(&cache), &makefile_path, makefile);
+        assert_eq!(
+            fresh,
+            Some(vec!["all".to_owned(), "other".to_owned()]),
+            "new mtime is parsed again"
+        );
+        let updated = fs::read_to_string(&cache).expect("cache should be written");
+        assert_eq!(updated.lines().count(), 1, "entry is replaced");
+        assert_ne!(stored, updated, "entry is updated");
+    }
+}
@@ -1,11 +1,13 @@
-use crate::file;
+use crate::{Icon, IconMode, Probe, file};
 use anyhow::Result;
 use std::{
     fs::{self, File},
-    io::{BufRead as _, BufReader, Error as IoError, ErrorKind},
+    io::{BufRead as _, BufReader, Error as IoError, ErrorKind, Result as IoResult},
 };
 
-#[derive(Debug)]
+/// Hypervisor type, as in `systemd-detect-virt --vm`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum VirtualizationType {
     Kvm,
     Amazon,
@@ -27,11 +29,63 @@ pub enum VirtualizationType {
     Other,
 }
 
+impl VirtualizationType {
+    /// Short name, the same `systemd-detect-virt` prints
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Kvm => "kvm",
+            Self::Amazon => "amazon",
+            Self::Qemu => "qemu",
+            Self::Bochs => "bochs",
+            Self::Xen => "xen",
+            Self::Uml => "uml",
+            Self::VMware => "vmware",
+            Self::Oracle => "oracle",
+            Self::Microsoft => "microsoft",
+            Self::Zvm => "zvm",
+            Self::Parallels => "parallels",
+            Self::Bhyve => "bhyve",
+            Self::Qnx => "qnx",
+            Self::Acrn => "acrn",
+            Self::PowerVM => "powervm",
+            Self::Apple => "apple",
+            Self::Sre => "sre",
+            Self::Other => "vm-other",
+        }
+    }
+}
+
+impl Icon for VirtualizationType {
+    fn icon(&self, mode: &IconMode) -> &'static str {
+        use IconMode::*;
+        match mode {
+            Text => "Virt",
+            Icons | MinimalIcons => match self {
+                Self::Amazon => " ",
+                Self::Microsoft => "󰖳 ",
+                Self::Apple => " ",
+                _ => " ",
+            },
+        }
+    }
+}
+
+/// Reads a sysfs-like file as a single value: without the trailing newline or NUL terminator
+fn read_value(probe: &Probe, path: &str) -> IoResult<String> {
+    Ok(fs::read_to_string(probe.path(path))?
+        .trim_end_matches(['\0', '\n'])
+        .to_owned())
+}
+
 #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
-fn detect_vm_cpuid() -> Option<VirtualizationType> {
+fn detect_vm_cpuid(probe: &Probe) -> Option<VirtualizationType> {
+    if !probe.is_host() {
+        return None;
+    }
     let cpuid = raw_cpuid::CpuId::new();
     let vendor_info = cpuid.get_vendor_info()?;
-    Some(match vendor_info.as_str() {
+    let vm = match vendor_info.as_str() {
         "XenVMMXenVMM" => VirtualizationType::Xen,
         "KVMKVMKVM" | "Linux KVM Hv" => VirtualizationType::Kvm,
         "TCGTCGTCGTCG" => VirtualizationType::Qemu,
@@ -42,54 +96,69 @@ fn detect_vm_cpuid() -> Option<VirtualizationType> {
         "ACRNACRNACRN" => VirtualizationType::Acrn,
         "SRESRESRESRE" => VirtualizationType::Sre,
         _ => VirtualizationType::Other,
-    })
+    };
+    probe.note(format!("cpuid: vendor is {:?}", vendor_info.as_str()));
+    Some(vm)
 }
 
 #[cfg(not(any(target_arch = "x86_64", target_arch = "x86")))]
-fn detect_vm_cpuid() -> Option<VirtualizationType> {
+fn detect_vm_cpuid(_: &Probe) -> Option<VirtualizationType> {
     None
 }
 
 #[expect(clippy::shadow_unrelated)] // send help please
-fn detect_vm_device_tree() -> Result<Option<VirtualizationType>> {
-    Ok(
-        match fs::read_to_string("/proc/device-tree/hypervisor/compatible") {
-            Ok(s) if s == "linux,kvm" => Some(VirtualizationType::Kvm),
-            Ok(s) if s.contains("xen") => Some(VirtualizationType::Xen),
-            Ok(s) if s.contains("vmware") => Some(VirtualizationType::VMware),
-            Ok(_) => Some(VirtualizationType::Other),
-            Err(e) if e.kind() == ErrorKind::NotFound => {
-                if fs::exists("/proc/device-tree/ibm,partition-name").unwrap_or(false)
-                    && fs::exists("/proc/device-tree/hmc-managed?").unwrap_or(false)
-                    && !fs::exists("/proc/device-tree/chosen/qemu,graphic-width").unwrap_or(false)
-                {
-                    Some(VirtualizationType::PowerVM)
-                } else {
-                    match file::exists_that("/proc/device-tree", |name| name.contains("fw-cfg")) {
-                        Ok(true) => Some(VirtualizationType::Qemu),
-                        Ok(false) => match fs::read_to_string("/proc/device-tree/compatible") {
-                            Ok(s) if s == "qemu,pseries" => Some(VirtualizationType::Qemu),
-                            Ok(_) => None,
-                            Err(e) if e.kind() == ErrorKind::NotFound => None,
-                            Err(e) => return Err(e.into()),
-                        },
-                        Err(e)
-                            if e.is::<IoError>()
-                                && e.downcast_ref::<IoError>().unwrap().kind()
-                                    == ErrorKind::NotFound =>
-                        {
-                            None
-                        }
-                        Err(e) => return Err(e),
+fn detect_vm_device_tree(probe: &Probe) -> Result<Option<VirtualizationType>> {
+    let vm = match read_value(probe, "/proc/device-tree/hypervisor/compatible") {
+        Ok(s) if s == "linux,kvm" => Some(VirtualizationType::Kvm),
+        Ok(s) if s.contains("xen") => Some(VirtualizationType::Xen),
+        Ok(s) if s.contains("vmware") => Some(VirtualizationType::VMware),
+        Ok(_) => Some(VirtualizationType::Other),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            if fs::exists(probe.path("/proc/device-tree/ibm,partition-name")).unwrap_or(false)
+                && fs::exists(probe.path("/proc/device-tree/hmc-managed?")).unwrap_or(false)
+                && !fs::exists(probe.path("/proc/device-tree/chosen/qemu,graphic-width"))
+                    .unwrap_or(false)
+            {
+                probe.note("device tree: IBM partition managed by HMC");
+                return Ok(Some(VirtualizationType::PowerVM));
+            }
+            match file::exists_that(probe.path("/proc/device-tree"), |name| {
+                name.contains("fw-cfg")
+            }) {
+                Ok(true) => {
+                    probe.note("device tree: QEMU fw-cfg node found");
+                    return Ok(Some(VirtualizationType::Qemu));
+                }
+                Ok(false) => match read_value(probe, "/proc/device-tree/compatible") {
+                    Ok(s) if s == "qemu,pseries" => {
+                        probe.note("device tree: machine is compatible with \"qemu,pseries\"");
+                        return Ok(Some(VirtualizationType::Qemu));
                     }
+                    Ok(_) => None,
+                    Err(e) if e.kind() == ErrorKind::NotFound => None,
+                    Err(e) => return Err(e.into()),
+                },
+                Err(e)
+                    if e.is::<IoError>()
+                        && e.downcast_ref::<IoError>().unwrap().kind() == ErrorKind::NotFound =>
+                {
+                    None
                 }
+                Err(e) => return Err(e),
             }
-            Err(e) => return Err(e.into()),
-        },
-    )
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if let Some(vm) = vm {
+        probe.note(format!(
+            "device tree: hypervisor node is compatible with {}",
+            vm.name()
+        ));
+    }
+    Ok(vm)
 }
 
-fn detect_vm_dmi_vendor() -> Result<Option<VirtualizationType>> {
+fn detect_vm_dmi_vendor(probe: &Probe) -> Result<Option<VirtualizationType>> {
     for path in [
         "/sys/class/dmi/id/product_name",
         "/sys/class/dmi/id/sys_vendor",
@@ -97,7 +166,7 @@ fn detect_vm_dmi_vendor() -> Result<Option<VirtualizationType>> {
         "/sys/class/dmi/id/bios_vendor",
         "/sys/class/dmi/id/product_version",
     ] {
-        match fs::read_to_string(path) {
+        match fs::read_to_string(probe.path(path)) {
             Ok(s) => {
                 for (vendor, vm) in [
                     ("KVM", VirtualizationType::Kvm),
@@ -117,6 +186,7 @@ fn detect_vm_dmi_vendor() -> Result<Option<VirtualizationType>> {
                     ("Apple Virtualization", VirtualizationType::Apple),
                 ] {
                     if s.starts_with(vendor) {
+                        probe.note(format!("dmi: {path} starts with {vendor:?}"));
                         return Ok(Some(vm));
                     }
                 }
@@ -128,45 +198,51 @@ fn detect_vm_dmi_vendor() -> Result<Option<VirtualizationType>> {
     Ok(None)
 }
 
-fn detect_vm_smbios_impl() -> Result<Option<bool>> {
-    Ok(fs::read("/sys/firmware/dmi/entries/0-0/raw")?
+fn detect_vm_smbios_impl(probe: &Probe) -> Result<Option<bool>> {
+    Ok(fs::read(probe.path("/sys/firmware/dmi/entries/0-0/raw"))?
         .get(19)
         .map(|x| ((x >> 4_i32) & 1) == 1))
 }
 
-fn detect_vm_smbios() -> Option<bool> {
-    detect_vm_smbios_impl().unwrap_or(None)
+fn detect_vm_smbios(probe: &Probe) -> Option<bool> {
+    let vm = detect_vm_smbios_impl(probe).unwrap_or(None);
+    if let Some(vm) = vm {
+        probe.note(format!("smbios: virtual machine bit is {}", u8::from(vm)));
+    }
+    vm
 }
 
-fn detect_vm_dmi() -> Result<Option<VirtualizationType>> {
-    Ok(match detect_vm_dmi_vendor()? {
-        Some(VirtualizationType::Amazon) => match detect_vm_smbios() {
+fn detect_vm_dmi(probe: &Probe) -> Result<Option<VirtualizationType>> {
+    Ok(match detect_vm_dmi_vendor(probe)? {
+        // DMI vendor is the same for EC2 virtual machines and bare-metal instances
+        Some(VirtualizationType::Amazon) => match detect_vm_smbios(probe) {
             Some(true) => Some(VirtualizationType::Amazon),
             Some(false) => None,
-            None => {
-                match fs::read_to_string("/sys/class/dmi/id/product_name") {
-                    Ok(s) => {
-                        let s = s.lines().next().unwrap_or_default();
-                        #[expect(clippy::case_sensitive_file_extension_comparisons)]
-                        (s.contains(".metal-") || s.ends_with(".metal")) // TODO
-                            .then_some(VirtualizationType::Amazon)
+            None => match read_value(probe, "/sys/class/dmi/id/product_name") {
+                Ok(s) => {
+                    #[expect(clippy::case_sensitive_file_extension_comparisons)]
+                    let metal = s.contains(".metal-") || s.ends_with(".metal");
+                    if metal {
+                        probe.note(format!("dmi: {s:?} is a bare-metal instance"));
                     }
-                    Err(_) => Some(VirtualizationType::Amazon),
+                    (!metal).then_some(VirtualizationType::Amazon)
                 }
-            }
+                // Virtual instances are much more common, so assume it is one
+                Err(_) => Some(VirtualizationType::Amazon),
+            },
         },
-        None if detect_vm_smbios().unwrap_or(false) => Some(VirtualizationType::Other),
+        None if detect_vm_smbios(probe).unwrap_or(false) => Some(VirtualizationType::Other),
         vm => vm,
     })
 }
 
 #[expect(clippy::shadow_unrelated)] // send help please
-fn detect_vm_xen_dom0() -> Result<bool> {
+fn detect_vm_xen_dom0(probe: &Probe) -> Result<bool> {
     Ok(
-        match fs::read_to_string("/sys/hypervisor/properties/features") {
+        match read_value(probe, "/sys/hypervisor/properties/features") {
             Ok(s) => (u64::from_str_radix(&s, 16)? >> 11) & 1 == 1,
             Err(e) if e.kind() == ErrorKind::NotFound => {
-                match fs::read_to_string("/proc/xen/capabilities") {
+                match fs::read_to_string(probe.path("/proc/xen/capabilities")) {
                     Ok(s) => s.contains("control_d"),
                     Err(e) if e.kind() == ErrorKind::NotFound => false,
                     Err(e) => return Err(e.into()),
@@ -177,37 +253,50 @@ fn detect_vm_xen_dom0() -> Result<bool> {
     )
 }
 
-fn detect_vm_xen() -> Result<Option<VirtualizationType>> {
-    Ok(fs::exists("/proc/xen")?.then_some(VirtualizationType::Xen))
+fn detect_vm_xen(probe: &Probe) -> Result<Option<VirtualizationType>> {
+    Ok(fs::exists(probe.path("/proc/xen"))?.then_some(VirtualizationType::Xen))
 }
 
-fn detect_vm_hypervisor() -> Result<Option<VirtualizationType>> {
-    Ok(match fs::read_to_string("/sys/hypervisor/type") {
-        Ok(s) if s == "xen" => Some(VirtualizationType::Xen),
-        Ok(_) => Some(VirtualizationType::Other),
+fn detect_vm_hypervisor(probe: &Probe) -> Result<Option<VirtualizationType>> {
+    Ok(match read_value(probe, "/sys/hypervisor/type") {
+        Ok(s) => {
+            probe.note(format!("sysfs: hypervisor type is {s:?}"));
+            Some(if s == "xen" {
+                VirtualizationType::Xen
+            } else {
+                VirtualizationType::Other
+            })
+        }
         Err(e) if e.kind() == ErrorKind::NotFound => None,
         Err(e) => return Err(e.into()),
     })
 }
 
-fn detect_vm_uml() -> Result<Option<VirtualizationType>> {
-    Ok(BufReader::new(File::open("/proc/cpuinfo")?)
+fn detect_vm_uml(probe: &Probe) -> Result<Option<VirtualizationType>> {
+    let file = match File::open(probe.path("/proc/cpuinfo")) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let uml = BufReader::new(file)
         .lines()
         .find_map(|x| Some(x.ok()?.strip_prefix("vendor_id\t: User Mode Linux")?.len()))
-        .is_some()
-        .then_some(VirtualizationType::Uml))
+        .is_some();
+    if uml {
+        probe.note("cpuinfo: vendor is User Mode Linux");
+    }
+    Ok(uml.then_some(VirtualizationType::Uml))
 }
 
-fn detect_vm_zvm() -> Result<Option<VirtualizationType>> {
-    Ok(match File::open("/proc/sysinfo") {
+fn detect_vm_zvm(probe: &Probe) -> Result<Option<VirtualizationType>> {
+    let vm = match File::open(probe.path("/proc/sysinfo")) {
         Ok(f) => BufReader::new(f).lines().find_map(|x| {
             match x
                 .ok()?
                 .strip_prefix("VM00 Control Program")?
-                .trim_start_matches(" \t")
+                .trim_start_matches([' ', '\t'])
                 .strip_prefix(':')?
-                .trim_start_matches(" \t")
-                .trim_start_matches('0')
+                .trim_start_matches([' ', '\t'])
                 .split_whitespace()
                 .next()
             {
@@ -218,11 +307,16 @@ fn detect_vm_zvm() -> Result<Option<VirtualizationType>> {
         }),
         Err(e) if e.kind() == ErrorKind::NotFound => None,
         Err(e) => return Err(e.into()),
-    })
+    };
+    if let Some(vm) = vm {
+        probe.note(format!("sysinfo: control program is {}", vm.name()));
+    }
+    Ok(vm)
 }
 
-pub fn detect_vm() -> Result<Option<VirtualizationType>> {
-    let dmi = detect_vm_dmi();
+/// Detects hypervisor the same way `systemd-detect-virt --vm` does
+pub fn detect_vm(probe: &Probe) -> Result<Option<VirtualizationType>> {
+    let dmi = detect_vm_dmi(probe);
     if let Ok(Some(
         VirtualizationType::Oracle
         | VirtualizationType::Xen
@@ -233,19 +327,21 @@ pub fn detect_vm() -> Result<Option<VirtualizationType>> {
         return dmi;
     }
 
-    if let uml @ Some(_) = detect_vm_uml()? {
+    if let uml @ Some(_) = detect_vm_uml(probe)? {
         return Ok(uml);
     }
 
     let mut xen_dom0 = false;
-    if let xen @ Some(VirtualizationType::Xen) = detect_vm_xen()? {
-        xen_dom0 = detect_vm_xen_dom0()?;
+    if let xen @ Some(VirtualizationType::Xen) = detect_vm_xen(probe)? {
+        xen_dom0 = detect_vm_xen_dom0(probe)?;
         if !xen_dom0 {
+            probe.note("xen: /proc/xen exists and this is not dom0");
             return Ok(xen);
         }
+        probe.note("xen: this is dom0, which is not a virtual machine by itself");
     }
 
-    match detect_vm_cpuid() {
+    match detect_vm_cpuid(probe) {
         Some(VirtualizationType::Other) => {}
         vm @ Some(_) => return Ok(vm),
         vm @ None if xen_dom0 => return Ok(vm),
@@ -259,25 +355,28 @@ pub fn detect_vm() -> Result<Option<VirtualizationType>> {
         _ => {}
     }
 
-    match detect_vm_hypervisor()? {
+    match detect_vm_hypervisor(probe)? {
         Some(VirtualizationType::Other) => other = true,
         vm @ Some(_) => return Ok(vm),
         _ => {}
     }
 
-    match detect_vm_device_tree()? {
+    match detect_vm_device_tree(probe)? {
         Some(VirtualizationType::Other) => other = true,
         vm @ Some(_) => return Ok(vm),
         _ => {}
     }
 
-    if let zvm @ Some(_) = detect_vm_zvm()? {
+    if let zvm @ Some(_) = detect_vm_zvm(probe)? {
         return Ok(zvm);
     }
 
     Ok(other.then_some(VirtualizationType::Other))
 }
 
+/// Container manager type, as in `systemd-detect-virt --container`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ContainerType {
     SystemdNspawn,
     LxcLibvirt,
@@ -292,30 +391,69 @@ pub enum ContainerType {
     Other,
 }
 
-fn running_in_cgroupns() -> Result<bool> {
-    if fs::exists("/proc/self/ns/cgroup").is_err() {
+impl ContainerType {
+    /// Short name, the same `systemd-detect-virt` prints
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::SystemdNspawn => "systemd-nspawn",
+            Self::LxcLibvirt => "lxc-libvirt",
+            Self::Lxc => "lxc",
+            Self::OpenVZ => "openvz",
+            Self::Docker => "docker",
+            Self::Podman => "podman",
+            Self::Rkt => "rkt",
+            Self::Wsl => "wsl",
+            Self::Proot => "proot",
+            Self::Pouch => "pouch",
+            Self::Other => "container-other",
+        }
+    }
+}
+
+impl Icon for ContainerType {
+    fn icon(&self, mode: &IconMode) -> &'static str {
+        use IconMode::*;
+        match mode {
+            Text => "Cont",
+            Icons | MinimalIcons => match self {
+                Self::Docker => " ",
+                Self::Podman => " ",
+                Self::Lxc | Self::LxcLibvirt => " ",
+                Self::Wsl => "󰖳 ",
+                Self::SystemdNspawn => " ",
+                _ => " ",
+            },
+        }
+    }
+}
+
+fn running_in_cgroupns(probe: &Probe) -> Result<bool> {
+    if !fs::exists(probe.path("/proc/self/ns/cgroup")).unwrap_or(false) {
         return Ok(false);
     }
 
     // Only cgroup v2 is supported right now, so no check if it _is_ v2 is needed
-    Ok(fs::exists("/sys/fs/cgroup/cgroup.events")?
-        && (fs::exists("/sys/fs/cgroup/cgroup.type")?
-            || !fs::exists("/sys/kernel/cgroup/features")?))
+    Ok(fs::exists(probe.path("/sys/fs/cgroup/cgroup.events"))?
+        && (fs::exists(probe.path("/sys/fs/cgroup/cgroup.type"))?
+            || !fs::exists(probe.path("/sys/kernel/cgroup/features"))?))
 }
 
-fn detect_container_files() -> Option<ContainerType> {
-    if let Ok(true) = fs::exists("/run/.containerenv") {
+fn detect_container_files(probe: &Probe) -> Option<ContainerType> {
+    if let Ok(true) = fs::exists(probe.path("/run/.containerenv")) {
+        probe.note("files: /run/.containerenv exists");
         return Some(ContainerType::Podman);
     }
-    if let Ok(true) = fs::exists("/.dockerenv") {
+    if let Ok(true) = fs::exists(probe.path("/.dockerenv")) {
+        probe.note("files: /.dockerenv exists");
         return Some(ContainerType::Docker);
     }
     None
 }
 
-fn translate_name(name: &str) -> ContainerType {
-    match name {
-        "oci" => detect_container_files().unwrap_or(ContainerType::Other),
+fn translate_name(probe: &Probe, name: &str) -> ContainerType {
+    match name.trim() {
+        "oci" => detect_container_files(probe).unwrap_or(ContainerType::Other),
         "lxc" => ContainerType::Lxc,
         "lxc-libvirt" => ContainerType::LxcLibvirt,
         "systemd-nspawn" => ContainerType::SystemdNspawn,
@@ -329,18 +467,24 @@ fn translate_name(name: &str) -> ContainerType {
     }
 }
 
-pub fn detect_container() -> Result<Option<ContainerType>> {
-    if let (Ok(true), Ok(false)) = (fs::exists("/proc/vz"), fs::exists("/proc/bc")) {
+/// Detects container manager the same way `systemd-detect-virt --container` does
+pub fn detect_container(probe: &Probe) -> Result<Option<ContainerType>> {
+    if let (Ok(true), Ok(false)) = (
+        fs::exists(probe.path("/proc/vz")),
+        fs::exists(probe.path("/proc/bc")),
+    ) {
+        probe.note("openvz: /proc/vz exists, but /proc/bc does not");
         return Ok(Some(ContainerType::OpenVZ));
     }
 
-    if let Ok(s) = fs::read_to_string("/proc/sys/kernel/osrelease")
+    if let Ok(s) = fs::read_to_string(probe.path("/proc/sys/kernel/osrelease"))
         && (s.contains("Microsoft") || s.contains("WSL"))
     {
+        probe.note(format!("wsl: kernel release is {:?}", s.trim_end()));
         return Ok(Some(ContainerType::Wsl));
     }
 
-    if let Ok(file) = File::open("/proc/self/status") {
+    if let Ok(file) = File::open(probe.path("/proc/self/status")) {
         if let Some(pid) = BufReader::new(file).lines().find_map(|line| {
             line.ok()?
                 .strip_prefix("TracerPid:\t")?
@@ -348,45 +492,427 @@ pub fn detect_container() -> Result<Option<ContainerType>> {
                 .map(|x| x.parse::<usize>().ok())
                 .next()?
         }) {
-            if let Ok(s) = fs::read_to_string(format!("/proc/{pid}/comm"))
+            if let Ok(s) = fs::read_to_string(probe.path(&format!("/proc/{pid}/comm")))
                 && s.starts_with("proot")
             {
+                probe.note(format!("proot: traced by proot with pid {pid}"));
                 return Ok(Some(ContainerType::Proot));
             }
         }
     }
 
-    match fs::read_to_string("/run/host/container-daemon") {
-        Ok(s) => {
-            return Ok(Some(translate_name(&s)));
-        }
-        Err(e) if e.kind() == ErrorKind::NotFound => {}
-        Err(e) => return Err(e.into()),
-    }
-
-    match fs::read_to_string("/run/systemd/container") {
-        Ok(s) => {
-            return Ok(Some(translate_name(&s)));
+    for path in ["/run/host/container-daemon", "/run/systemd/container"] {
+        match fs::read_to_string(probe.path(path)) {
+            Ok(s) => {
+                probe.note(format!("files: {path} says {:?}", s.trim()));
+                return Ok(Some(translate_name(probe, &s)));
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
         }
-        Err(e) if e.kind() == ErrorKind::NotFound => {}
-        Err(e) => return Err(e.into()),
     }
 
-    if let Ok(file) = File::open("/proc/1/environ") {
-        if let Some(name) = BufReader::new(file).split(0).find_map(|line| {
-            String::from_utf8(line.ok()?.strip_prefix(b"container=")?.to_vec()).ok()
-        }) {
-            return Ok(Some(translate_name(&name)));
-        }
+    if let Some(name) = pid1_environ(probe, "container") {
+        probe.note(format!("environ: init process has container={name:?}"));
+        return Ok(Some(translate_name(probe, &name)));
     }
 
-    if let ct @ Some(_) = detect_container_files() {
+    if let ct @ Some(_) = detect_container_files(probe) {
         return Ok(ct);
     }
 
-    if let Ok(true) = running_in_cgroupns() {
+    if let Ok(true) = running_in_cgroupns(probe) {
+        probe.note("cgroup: running in a separate cgroup namespace");
         return Ok(Some(ContainerType::Other));
     }
 
     Ok(None)
 }
+
+fn pid1_environ(probe: &Probe, key: &str) -> Option<String> {
+    let prefix = format!("{key}=");
+    BufReader::new(File::open(probe.path("/proc/1/environ")).ok()?)
+        .split(0)
+        .find_map(|line| {
+            String::from_utf8(line.ok()?.strip_prefix(prefix.as_bytes())?.to_vec()).ok()
+        })
+}
+
+/// Reads `key="value"` from podman's `/run/.containerenv`
+fn containerenv(probe: &Probe, key: &str) -> Option<String> {
+    BufReader::new(File::open(probe.path("/run/.containerenv")).ok()?)
+        .lines()
+        .find_map(|line| {
+            Some(
+                line.ok()?
+                    .strip_prefix(key)?
+                    .strip_prefix('=')?
+                    .trim_matches('"')
+                    .to_owned(),
+            )
+        })
+        .filter(|value| !value.is_empty())
+}
+
+/// Human-readable container name, since containers often get random hashes as hostnames
+///
+/// Distrobox exports `CONTAINER_ID`, podman and toolbox describe the container in
+/// `/run/.containerenv`, and systemd-nspawn passes `container_uuid` to the init process. Toolbox
+/// without any of these is at least marked with `/run/.toolboxenv`
+pub fn container_name(probe: &Probe) -> Option<String> {
+    probe
+        .var("CONTAINER_ID")
+        .filter(|name| !name.is_empty())
+        .or_else(|| containerenv(probe, "name"))
+        .or_else(|| {
+            // Image is a full reference like `registry.fedoraproject.org/fedora-toolbox:40`
+            let image = containerenv(probe, "image")?;
+            Some(image.rsplit('/').next().unwrap_or(&image).to_owned())
+        })
+        .or_else(|| {
+            let uuid = pid1_environ(probe, "container_uuid")?;
+            Some(uuid.get(..8).unwrap_or(&uuid).to_owned())
+        })
+        .or_else(|| file::exists(&probe.path("/run/.toolboxenv")).then(|| "toolbox".to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ContainerType, VirtualizationType, container_name, detect_container, detect_vm};
+    use crate::probe::fixture::Fixture;
+
+    /// Case name, files under the fake root, and expected result
+    type Case<'a, T> = (&'a str, &'a [(&'a str, &'a str)], T);
+
+    fn vm(files: &[(&str, &str)]) -> Option<VirtualizationType> {
+        detect_vm(&Fixture::new(files).probe()).expect("detection should not fail")
+    }
+
+    fn container(files: &[(&str, &str)]) -> Option<ContainerType> {
+        detect_container(&Fixture::new(files).probe()).expect("detection should not fail")
+    }
+
+    #[test]
+    fn vm_dmi() {
+        let smbios_vm = "\0".repeat(19) + "\x10";
+        let smbios_metal = "\0".repeat(20);
+        let cases: &[Case<Option<VirtualizationType>>] = &[
+            ("bare metal", &[], None),
+            (
+                "kvm",
+                &[("/sys/class/dmi/id/product_name", "KVM\n")],
+                Some(VirtualizationType::Kvm),
+            ),
+            (
+                "openstack",
+                &[("/sys/class/dmi/id/product_name", "OpenStack Nova\n")],
+                Some(VirtualizationType::Kvm),
+            ),
+            (
+                "qemu",
+                &[
+                    (
+                        "/sys/class/dmi/id/product_name",
+                        "Standard PC (Q35 + ICH9, 2009)\n",
+                    ),
+                    ("/sys/class/dmi/id/sys_vendor", "QEMU\n"),
+                ],
+                Some(VirtualizationType::Qemu),
+            ),
+            (
+                "vmware",
+                &[("/sys/class/dmi/id/sys_vendor", "VMware, Inc.\n")],
+                Some(VirtualizationType::VMware),
+            ),
+            (
+                "virtualbox",
+                &[("/sys/class/dmi/id/sys_vendor", "innotek GmbH\n")],
+                Some(VirtualizationType::Oracle),
+            ),
+            (
+                "parallels",
+                &[("/sys/class/dmi/id/board_vendor", "Parallels Software\n")],
+                Some(VirtualizationType::Parallels),
+            ),
+            (
+                "ec2 instance",
+                &[
+                    ("/sys/class/dmi/id/sys_vendor", "Amazon EC2\n"),
+                    ("/sys/firmware/dmi/entries/0-0/raw", &smbios_vm),
+                ],
+                Some(VirtualizationType::Amazon),
+            ),
+            (
+                "ec2 metal by smbios",
+                &[
+                    ("/sys/class/dmi/id/sys_vendor", "Amazon EC2\n"),
+                    ("/sys/firmware/dmi/entries/0-0/raw", &smbios_metal),
+                ],
+                None,
+            ),
+            (
+                "ec2 metal by product name",
+                &[
+                    ("/sys/class/dmi/id/product_name", "m5.metal\n"),
+                    ("/sys/class/dmi/id/sys_vendor", "Amazon EC2\n"),
+                ],
+                None,
+            ),
+            (
+                "ec2 instance by product name",
+                &[
+                    ("/sys/class/dmi/id/product_name", "m5.large\n"),
+                    ("/sys/class/dmi/id/sys_vendor", "Amazon EC2\n"),
+                ],
+                Some(VirtualizationType::Amazon),
+            ),
+            (
+                "unknown vendor with smbios vm bit",
+                &[("/sys/firmware/dmi/entries/0-0/raw", &smbios_vm)],
+                Some(VirtualizationType::Other),
+            ),
+        ];
+
+        for (name, files, expected) in cases {
+            assert_eq!(vm(files), *expected, "case {name}");
+        }
+    }
+
+    #[test]
+    fn vm_firmware() {
+        let cases: &[Case<Option<VirtualizationType>>] = &[
+            (
+                "xen domu",
+                &[("/proc/xen/", "")],
+                Some(VirtualizationType::Xen),
+            ),
+            (
+                "xen dom0 by capabilities",
+                &[("/proc/xen/capabilities", "control_d\n")],
+                None,
+            ),
+            (
+                "xen dom0 by features",
+                &[
+                    ("/proc/xen/", ""),
+                    ("/sys/hypervisor/properties/features", "00000801\n"),
+                ],
+                None,
+            ),
+            (
+                "xen by hypervisor type",
+                &[("/sys/hypervisor/type", "xen\n")],
+                Some(VirtualizationType::Xen),
+            ),
+            (
+                "unknown hypervisor type",
+                &[("/sys/hypervisor/type", "acme\n")],
+                Some(VirtualizationType::Other),
+            ),
+            (
+                "uml",
+                &[(
+                    "/proc/cpuinfo",
+                    "processor\t: 0\nvendor_id\t: User Mode Linux\n",
+                )],
+                Some(VirtualizationType::Uml),
+            ),
+            (
+                "z/vm",
+                &[(
+                    "/proc/sysinfo",
+                    "VM00 Name: LINUX1\nVM00 Control Program: z/VM    7.3.0\n",
+                )],
+                Some(VirtualizationType::Zvm),
+            ),
+            (
+                "kvm on s390",
+                &[("/proc/sysinfo", "VM00 Control Program: KVM/Linux\n")],
+                Some(VirtualizationType::Kvm),
+            ),
+            (
+                "kvm by device tree",
+                &[("/proc/device-tree/hypervisor/compatible", "linux,kvm\0")],
+                Some(VirtualizationType::Kvm),
+            ),
+            (
+                "xen by device tree",
+                &[(
+                    "/proc/device-tree/hypervisor/compatible",
+                    "xen,xen-4.17\0xen,xen\0",
+                )],
+                Some(VirtualizationType::Xen),
+            ),
+            (
+                "qemu by fw-cfg",
+                &[("/proc/device-tree/fw-cfg@9020000/", "")],
+                Some(VirtualizationType::Qemu),
+            ),
+            (
+                "qemu pseries",
+                &[("/proc/device-tree/compatible", "qemu,pseries\0")],
+                Some(VirtualizationType::Qemu),
+            ),
+            (
+                "powervm",
+                &[
+                    ("/proc/device-tree/ibm,partition-name", "lpar1\0"),
+                    ("/proc/device-tree/hmc-managed?", ""),
+                ],
+                Some(VirtualizationType::PowerVM),
+            ),
+            (
+                "kvm on power",
+                &[
+                    ("/proc/device-tree/ibm,partition-name", "lpar1\0"),
+                    ("/proc/device-tree/hmc-managed?", ""),
+                    ("/proc/device-tree/chosen/qemu,graphic-width", ""),
+                    ("/proc/device-tree/compatible", "qemu,pseries\0"),
+                ],
+                Some(VirtualizationType::Qemu),
+            ),
+        ];
+
+        for (name, files, expected) in cases {
+            assert_eq!(vm(files), *expected, "case {name}");
+        }
+    }
+
+    #[test]
+    fn container_matrix() {
+        let cases: &[Case<Option<ContainerType>>] = &[
+            ("host", &[], None),
+            (
+                "docker",
+                &[("/.dockerenv", "")],
+                Some(ContainerType::Docker),
+            ),
+            (
+                "podman",
+                &[("/run/.containerenv", "engine=\"podman-5.0.0\"\n")],
+                Some(ContainerType::Podman),
+            ),
+            (
+                "wsl",
+                &[(
+                    "/proc/sys/kernel/osrelease",
+                    "5.15.153.1-microsoft-standard-WSL2\n",
+                )],
+                Some(ContainerType::Wsl),
+            ),
+            (
+                "lxc",
+                &[("/proc/1/environ", "PATH=/usr/bin\0container=lxc\0")],
+                Some(ContainerType::Lxc),
+            ),
+            (
+                "lxc-libvirt",
+                &[("/proc/1/environ", "container=lxc-libvirt\0")],
+                Some(ContainerType::LxcLibvirt),
+            ),
+            (
+                "systemd-nspawn",
+                &[("/run/systemd/container", "systemd-nspawn\n")],
+                Some(ContainerType::SystemdNspawn),
+            ),
+            (
+                "flatpak-like container daemon",
+                &[("/run/host/container-daemon", "podman\n")],
+                Some(ContainerType::Podman),
+            ),
+            (
+                "oci with docker files",
+                &[("/run/systemd/container", "oci\n"), ("/.dockerenv", "")],
+                Some(ContainerType::Docker),
+            ),
+            (
+                "unknown oci",
+                &[("/run/systemd/container", "oci\n")],
+                Some(ContainerType::Other),
+            ),
+            ("openvz", &[("/proc/vz/", "")], Some(ContainerType::OpenVZ)),
+            ("openvz host", &[("/proc/vz/", ""), ("/proc/bc/", "")], None),
+            (
+                "proot",
+                &[
+                    ("/proc/self/status", "Name:\tbash\nTracerPid:\t42\n"),
+                    ("/proc/42/comm", "proot\n"),
+                ],
+                Some(ContainerType::Proot),
+            ),
+            (
+                "traced by debugger",
+                &[
+                    ("/proc/self/status", "Name:\tbash\nTracerPid:\t42\n"),
+                    ("/proc/42/comm", "gdb\n"),
+                ],
+                None,
+            ),
+            (
+                "cgroup namespace",
+                &[
+                    ("/proc/self/ns/cgroup", ""),
+                    ("/sys/fs/cgroup/cgroup.events", "populated 1\n"),
+                ],
+                Some(ContainerType::Other),
+            ),
+            (
+                "host cgroup root",
+                &[
+                    ("/proc/self/ns/cgroup", ""),
+                    ("/sys/fs/cgroup/cgroup.events", "populated 1\n"),
+                    ("/sys/kernel/cgroup/features", "nsdelegate\n"),
+                ],
+                None,
+            ),
+        ];
+
+        for (name, files, expected) in cases {
+            assert_eq!(container(files), *expected, "case {name}");
+        }
+    }
+
+    #[test]
+    fn container_names() {
+        let cases: &[Case<Option<&str>>] = &[
+            ("nothing", &[], None),
+            (
+                "podman name",
+                &[(
+                    "/run/.containerenv",
+                    "engine=\"podman-5.0.0\"\nname=\"fedora-toolbox-40\"\nimage=\"registry.fedoraproject.org/fedora-toolbox:40\"\n",
+                )],
+                Some("fedora-toolbox-40"),
+            ),
+            (
+                "podman image",
+                &[(
+                    "/run/.containerenv",
+                    "engine=\"podman-5.0.0\"\nimage=\"registry.fedoraproject.org/fedora-toolbox:40\"\n",
+                )],
+                Some("fedora-toolbox:40"),
+            ),
+            (
+                "nspawn uuid",
+                &[(
+                    "/proc/1/environ",
+                    "container=systemd-nspawn\0container_uuid=0f4e3b9a8c2d4b1e9f7a6c5d4e3f2a1b\0",
+                )],
+                Some("0f4e3b9a"),
+            ),
+            (
+                "toolbox marker",
+                &[("/run/.toolboxenv", "")],
+                Some("toolbox"),
+            ),
+        ];
+
+        for (name, files, expected) in cases {
+            assert_eq!(
+                container_name(&Fixture::new(files).probe()).as_deref(),
+                *expected,
+                "case {name}"
+            );
+        }
+    }
+}